@@ -3,7 +3,12 @@ use std::{
     io::BufWriter,
 };
 
-use markup_pdf_rs::{assets::Assets, document::Document, dom, style::Style};
+use markup_pdf_rs::{
+    assets::Assets,
+    document::Document,
+    dom,
+    style::{FontStyle, Style},
+};
 use taffy::{
     prelude::{Rect, Size},
     style::{
@@ -37,7 +42,7 @@ fn build_assets<'a>() -> Assets<'a> {
     assets.styles.add(
         "title",
         Style {
-            font_family: "bold",
+            font_weight: 700,
             font_size: 30.0,
             size: Size {
                 width: Dimension::Auto,
@@ -76,7 +81,7 @@ fn build_assets<'a>() -> Assets<'a> {
     assets.styles.add(
         "summary-title",
         Style {
-            font_family: "bold",
+            font_weight: 700,
             flex_grow: 1.0,
             size: Size {
                 width: Dimension::Auto,
@@ -178,14 +183,16 @@ fn build_assets<'a>() -> Assets<'a> {
         "default",
         include_bytes!("assets/fonts/ShipporiMincho-Regular.ttf").as_slice(),
     );
-    assets.fonts.add(
-        "bold",
+    assets.fonts.add_variant(
+        "default",
+        700,
+        FontStyle::Normal,
         include_bytes!("assets/fonts/ShipporiMincho-Bold.ttf").as_slice(),
     );
-    assets.images.add(
-        "food",
-        image::open("examples/assets/images/food.jpg").unwrap(),
-    );
+    assets
+        .images
+        .add_bytes("food", include_bytes!("assets/images/food.jpg"))
+        .unwrap();
     assets
 }
 
@@ -3,7 +3,12 @@ use std::{
     io::BufWriter,
 };
 
-use markup_pdf_rs::{assets::Assets, document::Document, dom, style::Style};
+use markup_pdf_rs::{
+    assets::Assets,
+    document::Document,
+    dom,
+    style::{FontStyle, Style},
+};
 use taffy::{
     prelude::{Rect, Size},
     style::{
@@ -54,7 +59,7 @@ fn build_assets<'a>() -> Assets<'a> {
         "title",
         Style {
             flex_grow: 1.0,
-            font_family: "bold",
+            font_weight: 700,
             font_size: 20.0,
             ..Style::DEFAULT
         },
@@ -250,8 +255,10 @@ fn build_assets<'a>() -> Assets<'a> {
         "default",
         include_bytes!("assets/fonts/ShipporiMincho-Regular.ttf").as_slice(),
     );
-    assets.fonts.add(
-        "bold",
+    assets.fonts.add_variant(
+        "default",
+        700,
+        FontStyle::Normal,
         include_bytes!("assets/fonts/ShipporiMincho-Bold.ttf").as_slice(),
     );
     assets
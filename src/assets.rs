@@ -1,8 +1,26 @@
-use printpdf::{image_crate::DynamicImage, Image, IndirectFontRef, PdfDocumentReference};
-use rusttype::Font;
-use std::{collections::HashMap, error::Error, io::Cursor};
+use image::{
+    codecs::jpeg::JpegDecoder, ColorType, DynamicImage, GenericImageView, ImageDecoder, ImageError,
+    ImageFormat,
+};
+use lopdf::{dictionary, ObjectId, Stream};
+use printpdf::IndirectFontRef;
+use rustybuzz::{Face, Feature};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    fmt,
+    io::Cursor,
+};
+use ttf_parser::{name_id, GlyphId};
 
-use crate::style::Style;
+use crate::{
+    error::Error,
+    output::{EmbeddedFont, EmbeddedImage},
+    shaping::{get_glyph_texts, shape, Glyph},
+    style::{FontStyle, Style},
+    subset::{subset_font, to_subset_tag},
+    svg::SvgImage,
+};
 
 #[derive(Debug, Default)]
 pub struct Assets<'a> {
@@ -12,67 +30,474 @@ pub struct Assets<'a> {
 }
 
 impl<'a> Assets<'a> {
-    pub fn prepare(&mut self, pdf_doc: &PdfDocumentReference) -> Result<(), Box<dyn Error>> {
-        self.fonts.prepare(pdf_doc)?;
-        Ok(())
+    pub fn prepare(&mut self) -> Result<(), Error> {
+        self.images.prepare();
+        self.fonts.prepare()
     }
 }
 
-#[derive(Debug)]
 pub struct FontAssetItem<'a> {
-    pub printpdf: IndirectFontRef,
-    pub rusttype: Font<'a>,
+    pub rustybuzz: Face<'a>,
+    family: String,
+    font_bytes: &'a [u8],
+    // The name of the font resource in pages, fonts are embedded by `Output` instead of printpdf.
+    name: String,
+    font_ref: IndirectFontRef,
+    // Glyph ids written in the document with the texts they are made from,
+    // only these are embedded when saving.
+    used_glyphs: RefCell<BTreeMap<u16, String>>,
+}
+
+impl<'a> fmt::Debug for FontAssetItem<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FontAssetItem")
+            .field("family", &self.family)
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
 }
 
 impl<'a> FontAssetItem<'a> {
-    pub fn new(
-        pdf_doc: &PdfDocumentReference,
-        font_bytes: &'a [u8],
-    ) -> Result<Self, Box<dyn Error>> {
+    fn new(family: &str, font_bytes: &'a [u8], name: String) -> Result<Self, Error> {
         Ok(Self {
-            printpdf: pdf_doc.add_external_font(Cursor::new(font_bytes))?,
-            rusttype: Font::try_from_bytes(font_bytes)
-                .ok_or("Failed to load the font as rusttype")?,
+            rustybuzz: Face::from_slice(font_bytes, 0)
+                .ok_or_else(|| Error::InvalidFontAsset(family.to_string()))?,
+            family: family.to_string(),
+            font_bytes,
+            font_ref: IndirectFontRef::new(name.as_str()),
+            name,
+            used_glyphs: RefCell::new(BTreeMap::new()),
+        })
+    }
+
+    pub fn has_glyph(&self, c: char) -> bool {
+        self.rustybuzz.glyph_index(c).is_some()
+    }
+
+    pub fn shape(&self, text: &str, features: &[Feature], is_rtl: bool) -> Vec<Glyph> {
+        shape(&self.rustybuzz, text, features, is_rtl)
+    }
+
+    // Returns the ascender and the descender in em.
+    pub fn get_vertical_metrics(&self) -> (f64, f64) {
+        let units_per_em = f64::from(self.rustybuzz.units_per_em());
+        (
+            f64::from(self.rustybuzz.ascender()) / units_per_em,
+            f64::from(self.rustybuzz.descender()) / units_per_em,
+        )
+    }
+
+    // Must be called with the glyphs shaped from the text before they are written.
+    pub fn embed(&self, glyphs: &[Glyph], text: &str) -> Result<&IndirectFontRef, Error> {
+        let mut used_glyphs = self.used_glyphs.borrow_mut();
+        for (id, text) in get_glyph_texts(glyphs, text) {
+            let used_text = used_glyphs.entry(id).or_default();
+            if used_text.is_empty() {
+                *used_text = text.to_string();
+            }
+        }
+        Ok(&self.font_ref)
+    }
+
+    // Returns the PostScript name of the font, or the resource name if the font has no name.
+    fn get_postscript_name(&self) -> String {
+        self.rustybuzz
+            .names()
+            .into_iter()
+            .filter(|name| name.name_id == name_id::POST_SCRIPT_NAME)
+            .find_map(|name| name.to_string())
+            .unwrap_or_else(|| self.name.clone())
+    }
+
+    // Returns None if no texts are written in the font.
+    pub fn to_embedded_font(&self) -> Option<EmbeddedFont> {
+        let used_glyphs = self.used_glyphs.borrow();
+        if used_glyphs.is_empty() {
+            return None;
+        }
+        let units_per_em = f64::from(self.rustybuzz.units_per_em());
+        let to_em = |value: i16| f64::from(value) * 1000.0 / units_per_em;
+        let glyphs = used_glyphs
+            .iter()
+            .map(|(id, text)| {
+                let width = self.rustybuzz.glyph_hor_advance(GlyphId(*id)).unwrap_or(0);
+                (
+                    *id,
+                    (f64::from(width) * 1000.0 / units_per_em, text.clone()),
+                )
+            })
+            .collect();
+        let glyph_ids = used_glyphs.keys().copied().collect();
+        // The name of a subset is tagged so that it's not confused with the whole font.
+        let (base_font, font_file) = match subset_font(self.font_bytes, &glyph_ids) {
+            Some(subset) => (
+                format!(
+                    "{}+{}",
+                    to_subset_tag(&glyph_ids),
+                    self.get_postscript_name()
+                ),
+                subset,
+            ),
+            None => (self.get_postscript_name(), self.font_bytes.to_vec()),
+        };
+        let bbox = self.rustybuzz.global_bounding_box();
+        Some(EmbeddedFont {
+            name: self.name.clone(),
+            base_font,
+            font_file,
+            ascent: to_em(self.rustybuzz.ascender()),
+            descent: to_em(self.rustybuzz.descender()),
+            bbox: [
+                to_em(bbox.x_min),
+                to_em(bbox.y_min),
+                to_em(bbox.x_max),
+                to_em(bbox.y_max),
+            ],
+            glyphs,
         })
     }
 }
 
+#[derive(Debug)]
+struct FontVariant<'a> {
+    weight: u16,
+    style: FontStyle,
+    font_bytes: &'a [u8],
+    // Created by `prepare` for each document.
+    item: Option<FontAssetItem<'a>>,
+}
+
+// Finds the nearest weight same as the font matching algorithm of CSS.
+// https://www.w3.org/TR/css-fonts-4/#font-style-matching
+fn find_nearest_weight(weights: &[u16], desired: u16) -> Option<u16> {
+    let lighter = || weights.iter().filter(|w| **w < desired).max().copied();
+    let heavier = |from: u16| weights.iter().filter(|w| **w > from).min().copied();
+    if weights.contains(&desired) {
+        return Some(desired);
+    }
+    if (400..=500).contains(&desired) {
+        let up_to_500 = weights
+            .iter()
+            .filter(|w| desired < **w && **w <= 500)
+            .min()
+            .copied();
+        up_to_500.or_else(lighter).or_else(|| heavier(500))
+    } else if desired < 400 {
+        lighter().or_else(|| heavier(desired))
+    } else {
+        heavier(desired).or_else(lighter)
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct FontAsset<'a> {
-    fonts: HashMap<&'a str, &'a [u8]>,
-    cache: HashMap<&'a str, FontAssetItem<'a>>,
+    families: HashMap<&'a str, Vec<FontVariant<'a>>>,
 }
 
 impl<'a> FontAsset<'a> {
-    pub fn add(&mut self, key: &'a str, font_bytes: &'a [u8]) {
-        self.fonts.insert(key, font_bytes);
+    // Adds a font as the normal variant of the family.
+    pub fn add(&mut self, family: &'a str, font_bytes: &'a [u8]) {
+        self.add_variant(family, 400, FontStyle::Normal, font_bytes);
     }
 
-    pub fn get(&self, key: &'a str) -> Option<&FontAssetItem> {
-        self.cache.get(key)
+    pub fn add_variant(
+        &mut self,
+        family: &'a str,
+        weight: u16,
+        style: FontStyle,
+        font_bytes: &'a [u8],
+    ) {
+        let variants = self.families.entry(family).or_default();
+        variants.retain(|variant| variant.weight != weight || variant.style != style);
+        variants.push(FontVariant {
+            weight,
+            style,
+            font_bytes,
+            item: None,
+        });
     }
 
-    pub fn prepare(&mut self, pdf_doc: &PdfDocumentReference) -> Result<(), Box<dyn Error>> {
-        for (key, font_bytes) in &self.fonts {
-            self.cache
-                .insert(key, FontAssetItem::new(pdf_doc, font_bytes)?);
+    // Italic falls back to normal and vice versa when the family has no variant of the style.
+    fn find_variant(
+        &self,
+        family: &str,
+        weight: u16,
+        style: FontStyle,
+    ) -> Option<&FontVariant<'a>> {
+        let variants = self.families.get(family)?;
+        let has_style = variants.iter().any(|variant| variant.style == style);
+        let candidates: Vec<&FontVariant<'a>> = variants
+            .iter()
+            .filter(|variant| !has_style || variant.style == style)
+            .collect();
+        let weights: Vec<u16> = candidates.iter().map(|variant| variant.weight).collect();
+        let weight = find_nearest_weight(&weights, weight)?;
+        candidates
+            .into_iter()
+            .find(|variant| variant.weight == weight)
+    }
+
+    // Returns None before `prepare` is called.
+    pub fn get(&self, family: &str, weight: u16, style: FontStyle) -> Option<&FontAssetItem<'a>> {
+        self.find_variant(family, weight, style)?.item.as_ref()
+    }
+
+    // The family can be a list separated by commas same as CSS,
+    // and fonts are returned in the order of the list.
+    pub fn get_fallbacks(
+        &self,
+        font_family: &str,
+        weight: u16,
+        style: FontStyle,
+    ) -> Vec<&FontAssetItem<'a>> {
+        font_family
+            .split(',')
+            .filter_map(|family| self.get(family.trim(), weight, style))
+            .collect()
+    }
+
+    // Fonts are named `F0`, `F1` and so on in the order of families,
+    // so that the same document is built with the same names.
+    pub fn prepare(&mut self) -> Result<(), Error> {
+        let mut families: Vec<_> = self.families.iter_mut().collect();
+        families.sort_by_key(|(family, _)| **family);
+        let variants = families.into_iter().flat_map(|(family, variants)| {
+            variants.iter_mut().map(move |variant| (family, variant))
+        });
+        for (index, (family, variant)) in variants.enumerate() {
+            let item = FontAssetItem::new(family, variant.font_bytes, format!("F{}", index))?;
+            variant.item = Some(item);
         }
         Ok(())
     }
+
+    // Must be called after all texts are written.
+    pub fn to_embedded_fonts(&self) -> Vec<EmbeddedFont> {
+        self.families
+            .values()
+            .flatten()
+            .filter_map(|variant| variant.item.as_ref()?.to_embedded_font())
+            .collect()
+    }
+}
+
+fn build_image_stream(width: u32, height: u32, color_space: &str, data: Vec<u8>) -> Stream {
+    let dict = dictionary! {
+        "Type" => "XObject",
+        "Subtype" => "Image",
+        "Width" => width,
+        "Height" => height,
+        "ColorSpace" => color_space,
+        "BitsPerComponent" => 8,
+    };
+    Stream::new(dict, data)
+}
+
+fn build_pixel_stream(width: u32, height: u32, color_space: &str, pixels: Vec<u8>) -> Stream {
+    let mut stream = build_image_stream(width, height, color_space, pixels);
+    // Pixels are compressed with FlateDecode,
+    // it's kept uncompressed only if it doesn't get smaller.
+    let _ = stream.compress();
+    stream
+}
+
+// Returns the number of color components in the frame header
+// and whether the file has the APP14 marker of Adobe.
+fn read_jpeg_header(bytes: &[u8]) -> Option<(u8, bool)> {
+    let mut has_adobe_marker = false;
+    let mut offset = 2;
+    while offset + 4 <= bytes.len() {
+        if bytes[offset] != 0xFF {
+            return None;
+        }
+        let marker = bytes[offset + 1];
+        let length = usize::from(u16::from_be_bytes([bytes[offset + 2], bytes[offset + 3]]));
+        let segment = bytes.get(offset + 4..offset + 2 + length)?;
+        match marker {
+            0xEE if segment.starts_with(b"Adobe") => has_adobe_marker = true,
+            // Start of frame markers except DHT, JPG and DAC.
+            0xC0..=0xCF if ![0xC4, 0xC8, 0xCC].contains(&marker) => {
+                return Some((*segment.get(5)?, has_adobe_marker));
+            }
+            _ => {}
+        }
+        offset += 2 + length;
+    }
+    None
+}
+
+#[derive(Debug, Clone)]
+pub struct ImageAssetItem {
+    pub width: u32,
+    pub height: u32,
+    // Built once when the image is added, and embedded once for all pages.
+    pub xobject: Stream,
+    // PDF images can't have an alpha channel,
+    // so it's embedded as a separated grayscale image called a soft mask.
+    pub smask: Option<Stream>,
+}
+
+impl ImageAssetItem {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ImageError> {
+        match image::guess_format(bytes)? {
+            ImageFormat::Jpeg => Self::from_jpeg(bytes),
+            _ => Ok(Self::from_dynamic_image(&image::load_from_memory(bytes)?)),
+        }
+    }
+
+    // JPEG can be embedded as it is because PDF supports DCTDecode,
+    // so we decode only the header to know the size and color space.
+    // CMYK files made by Adobe store inverted colors,
+    // so they are decoded and embedded as pixels instead.
+    fn from_jpeg(bytes: &[u8]) -> Result<Self, ImageError> {
+        let decoder = JpegDecoder::new(Cursor::new(bytes))?;
+        let (width, height) = decoder.dimensions();
+        let color_space = match read_jpeg_header(bytes) {
+            Some((1, _)) => "DeviceGray",
+            Some((4, false)) => "DeviceCMYK",
+            Some((4, true)) => {
+                return Ok(Self::from_dynamic_image(&DynamicImage::from_decoder(
+                    decoder,
+                )?))
+            }
+            _ => "DeviceRGB",
+        };
+        let mut xobject = build_image_stream(width, height, color_space, bytes.to_vec());
+        xobject.dict.set("Filter", "DCTDecode");
+        Ok(Self {
+            width,
+            height,
+            xobject,
+            smask: None,
+        })
+    }
+
+    pub fn from_dynamic_image(image: &DynamicImage) -> Self {
+        let (width, height) = image.dimensions();
+        let color = image.color();
+        let xobject = match color {
+            ColorType::L8 | ColorType::La8 | ColorType::L16 | ColorType::La16 => {
+                build_pixel_stream(width, height, "DeviceGray", image.to_luma8().into_raw())
+            }
+            _ => build_pixel_stream(width, height, "DeviceRGB", image.to_rgb8().into_raw()),
+        };
+        let smask = if color.has_alpha() {
+            Self::build_smask(image)
+        } else {
+            None
+        };
+        Self {
+            width,
+            height,
+            xobject,
+            smask,
+        }
+    }
+
+    fn build_smask(image: &DynamicImage) -> Option<Stream> {
+        let alpha: Vec<u8> = image.to_rgba8().pixels().map(|pixel| pixel.0[3]).collect();
+        if alpha.iter().all(|value| *value == u8::MAX) {
+            return None;
+        }
+        let (width, height) = image.dimensions();
+        Some(build_pixel_stream(width, height, "DeviceGray", alpha))
+    }
+
+    // Adds the image to the document and returns the id of the XObject.
+    // The soft mask is added first so that the XObject refers to it.
+    pub fn embed(&self, doc: &mut lopdf::Document) -> ObjectId {
+        let mut xobject = self.xobject.clone();
+        if let Some(smask) = &self.smask {
+            xobject.dict.set("SMask", doc.add_object(smask.clone()));
+        }
+        doc.add_object(xobject)
+    }
+}
+
+// Names are different from `X0`, `X1` and so on that printpdf gives to its XObjects.
+fn to_image_name(index: usize) -> String {
+    format!("Im{}", index)
 }
 
 #[derive(Debug, Default)]
 pub struct ImageAsset<'a> {
-    images: HashMap<&'a str, DynamicImage>,
+    images: HashMap<&'a str, ImageAssetItem>,
+    svgs: HashMap<&'a str, SvgImage>,
+    // Keys of images drawn in the document in the order they are first drawn,
+    // and the index is used as the name of the XObject resource.
+    used_keys: RefCell<Vec<String>>,
 }
 
 impl<'a> ImageAsset<'a> {
-    pub fn get(&self, key: &str) -> Option<Image> {
-        self.images.get(key).map(Image::from_dynamic_image)
+    pub fn get(&self, key: &str) -> Option<&ImageAssetItem> {
+        self.images.get(key)
+    }
+
+    pub fn get_svg(&self, key: &str) -> Option<&SvgImage> {
+        self.svgs.get(key)
+    }
+
+    pub fn is_svg(&self, key: &str) -> bool {
+        self.svgs.contains_key(key)
+    }
+
+    pub fn get_size(&self, key: &str) -> Option<(f64, f64)> {
+        if let Some(svg) = self.svgs.get(key) {
+            return Some((svg.width, svg.height));
+        }
+        self.images
+            .get(key)
+            .map(|image| (f64::from(image.width), f64::from(image.height)))
     }
 
-    pub fn add(&mut self, key: &'a str, style: DynamicImage) {
-        self.images.insert(key, style);
+    pub fn add(&mut self, key: &'a str, image: DynamicImage) {
+        self.images
+            .insert(key, ImageAssetItem::from_dynamic_image(&image));
+    }
+
+    // Prefer this to `add` for JPEG files
+    // because they are embedded without re-encoding.
+    pub fn add_bytes(&mut self, key: &'a str, bytes: &[u8]) -> Result<(), ImageError> {
+        self.images.insert(key, ImageAssetItem::from_bytes(bytes)?);
+        Ok(())
+    }
+
+    // SVG images are drawn as vector graphics instead of pixels.
+    pub fn add_svg(&mut self, key: &'a str, bytes: &[u8]) -> Result<(), usvg::Error> {
+        self.svgs.insert(key, SvgImage::from_bytes(bytes)?);
+        Ok(())
+    }
+
+    pub fn prepare(&self) {
+        self.used_keys.borrow_mut().clear();
+    }
+
+    // Returns the name of the XObject resource to draw the image with `Do`,
+    // which is the same on all pages.
+    pub fn use_image(&self, key: &str) -> Option<String> {
+        self.images.get(key)?;
+        let mut used_keys = self.used_keys.borrow_mut();
+        let index = match used_keys.iter().position(|used_key| used_key == key) {
+            Some(index) => index,
+            None => {
+                used_keys.push(key.to_string());
+                used_keys.len() - 1
+            }
+        };
+        Some(to_image_name(index))
+    }
+
+    // Must be called after all images are drawn.
+    pub fn to_embedded_images(&self) -> Vec<EmbeddedImage> {
+        self.used_keys
+            .borrow()
+            .iter()
+            .enumerate()
+            .map(|(index, key)| EmbeddedImage {
+                name: to_image_name(index),
+                image: self.images[key.as_str()].clone(),
+            })
+            .collect()
     }
 }
 
@@ -82,7 +507,7 @@ pub struct StyleAsset<'a> {
 }
 
 impl<'a> StyleAsset<'a> {
-    pub fn get(&self, key: &str) -> Option<&Style> {
+    pub fn get(&self, key: &str) -> Option<&Style<'a>> {
         self.styles.get(key)
     }
 
@@ -90,3 +515,147 @@ impl<'a> StyleAsset<'a> {
         self.styles.insert(key, style);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use image::{RgbImage, Rgba, RgbaImage};
+
+    use super::*;
+
+    #[test]
+    fn it_finds_the_exact_weight() {
+        assert_eq!(find_nearest_weight(&[400, 700], 700), Some(700));
+    }
+
+    #[test]
+    fn it_finds_lighter_weights_first_when_desired_weight_is_light() {
+        assert_eq!(find_nearest_weight(&[100, 500, 700], 300), Some(100));
+        assert_eq!(find_nearest_weight(&[500, 700], 300), Some(500));
+    }
+
+    #[test]
+    fn it_finds_500_first_when_desired_weight_is_400() {
+        assert_eq!(find_nearest_weight(&[300, 500, 700], 400), Some(500));
+        assert_eq!(find_nearest_weight(&[300, 600], 400), Some(300));
+        assert_eq!(find_nearest_weight(&[600, 900], 450), Some(600));
+    }
+
+    #[test]
+    fn it_finds_heavier_weights_first_when_desired_weight_is_bold() {
+        assert_eq!(find_nearest_weight(&[400, 800, 900], 600), Some(800));
+        assert_eq!(find_nearest_weight(&[300, 400], 700), Some(400));
+    }
+
+    #[test]
+    fn it_finds_nothing_when_there_are_no_weights() {
+        assert_eq!(find_nearest_weight(&[], 400), None);
+    }
+
+    fn build_jpeg_header(components: u8, has_adobe_marker: bool) -> Vec<u8> {
+        let mut bytes = vec![0xFF, 0xD8];
+        if has_adobe_marker {
+            bytes.extend([0xFF, 0xEE, 0x00, 0x0E]);
+            bytes.extend(b"Adobe");
+            bytes.extend([0x00, 0x64, 0x00, 0x00, 0x00, 0x00, 0x02]);
+        }
+        bytes.extend([
+            0xFF,
+            0xC0,
+            0x00,
+            8 + 3 * components,
+            0x08,
+            0x00,
+            0x01,
+            0x00,
+            0x01,
+        ]);
+        bytes.push(components);
+        for id in 1..=components {
+            bytes.extend([id, 0x11, 0x00]);
+        }
+        bytes
+    }
+
+    #[test]
+    fn it_reads_color_components_of_jpeg_files() {
+        assert_eq!(
+            read_jpeg_header(&build_jpeg_header(1, false)),
+            Some((1, false))
+        );
+        assert_eq!(
+            read_jpeg_header(&build_jpeg_header(3, false)),
+            Some((3, false))
+        );
+        assert_eq!(
+            read_jpeg_header(&build_jpeg_header(4, false)),
+            Some((4, false))
+        );
+    }
+
+    #[test]
+    fn it_reads_the_adobe_marker_of_jpeg_files() {
+        assert_eq!(
+            read_jpeg_header(&build_jpeg_header(4, true)),
+            Some((4, true))
+        );
+    }
+
+    #[test]
+    fn it_reads_nothing_from_broken_jpeg_files() {
+        let bytes = build_jpeg_header(3, false);
+        assert_eq!(read_jpeg_header(&bytes[..6]), None);
+        assert_eq!(read_jpeg_header(&[0xFF, 0xD8, 0x00]), None);
+    }
+
+    // Lopdf doesn't decompress images.
+    fn decompress_pixels(stream: &Stream) -> Vec<u8> {
+        let mut stream = stream.clone();
+        stream.dict.remove(b"Subtype");
+        stream.decompressed_content().unwrap_or(stream.content)
+    }
+
+    #[test]
+    fn it_builds_a_soft_mask_from_alpha_channels() {
+        let image = RgbaImage::from_fn(16, 16, |x, _| Rgba([0, 0, 0, x as u8]));
+        let smask = ImageAssetItem::build_smask(&DynamicImage::ImageRgba8(image)).unwrap();
+        assert_eq!(
+            smask
+                .dict
+                .get(b"ColorSpace")
+                .unwrap()
+                .as_name_str()
+                .unwrap(),
+            "DeviceGray"
+        );
+        assert_eq!(smask.dict.get(b"Width").unwrap().as_i64().unwrap(), 16);
+        let alpha = decompress_pixels(&smask);
+        assert_eq!(alpha.len(), 16 * 16);
+        assert_eq!(&alpha[..3], [0, 1, 2]);
+    }
+
+    #[test]
+    fn it_builds_no_soft_masks_from_opaque_images() {
+        let image = RgbaImage::from_pixel(16, 16, Rgba([0, 0, 0, u8::MAX]));
+        assert!(ImageAssetItem::build_smask(&DynamicImage::ImageRgba8(image)).is_none());
+        let image =
+            ImageAssetItem::from_dynamic_image(&DynamicImage::ImageRgb8(RgbImage::new(16, 16)));
+        assert!(image.smask.is_none());
+    }
+
+    #[test]
+    fn it_refers_to_the_soft_mask_from_the_xobject() {
+        let image = RgbaImage::from_pixel(16, 8, Rgba([0, 0, 0, 128]));
+        let image = ImageAssetItem::from_dynamic_image(&DynamicImage::ImageRgba8(image));
+        let mut doc = lopdf::Document::with_version("1.5");
+        let id = image.embed(&mut doc);
+        let xobject = &doc.get_object(id).unwrap().as_stream().unwrap().dict;
+        let smask_id = xobject.get(b"SMask").unwrap().as_reference().unwrap();
+        let smask = &doc.get_object(smask_id).unwrap().as_stream().unwrap().dict;
+        for key in [b"Width".as_slice(), b"Height"] {
+            assert_eq!(
+                smask.get(key).unwrap().as_i64().unwrap(),
+                xobject.get(key).unwrap().as_i64().unwrap()
+            );
+        }
+    }
+}
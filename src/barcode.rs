@@ -0,0 +1,315 @@
+use std::str::FromStr;
+
+use printpdf::{Mm, PdfPageReference};
+
+use crate::{
+    assets::Assets,
+    clip::Clip,
+    dom::{get_attr, get_required_attr, validate_name},
+    error::Error,
+    layout::{LayoutNode, LayoutTree},
+    path::{fill_rects, get_fill_color},
+    rect::Rect,
+    text::{get_text_width, to_mm_size, use_text},
+};
+
+// Widths of bars and spaces for each symbol value of Code 128.
+// The last one is the stop pattern.
+const CODE128_PATTERNS: [&str; 107] = [
+    "212222", "222122", "222221", "121223", "121322", "131222", "122213", "122312", "132212",
+    "221213", "221312", "231212", "112232", "122132", "122231", "113222", "123122", "123221",
+    "223211", "221132", "221231", "213212", "223112", "312131", "311222", "321122", "321221",
+    "312212", "322112", "322211", "212123", "212321", "232121", "111323", "131123", "131321",
+    "112313", "132113", "132311", "211313", "231113", "231311", "112133", "112331", "132131",
+    "113123", "113321", "133121", "313121", "211331", "231131", "213113", "213311", "213131",
+    "311123", "311321", "331121", "312113", "312311", "332111", "314111", "221411", "431111",
+    "111224", "111422", "121124", "121421", "141122", "141221", "112214", "112412", "122114",
+    "122411", "142112", "142211", "241211", "221114", "413111", "241112", "134111", "111242",
+    "121142", "121241", "114212", "124112", "124211", "411212", "421112", "421211", "212141",
+    "214121", "412121", "111143", "111341", "131141", "114113", "114311", "411113", "411311",
+    "113141", "114131", "311141", "411131", "211412", "211214", "211232", "2331112",
+];
+const CODE128_START_B: usize = 104;
+const CODE128_START_C: usize = 105;
+const CODE128_STOP: usize = 106;
+const CODE128_QUIET_ZONE: usize = 10;
+
+// L-codes of EAN-13, R-codes are the complement
+// and G-codes are the reverse of R-codes.
+const EAN13_L_CODES: [&str; 10] = [
+    "0001101", "0011001", "0010011", "0111101", "0100011", "0110001", "0101111", "0111011",
+    "0110111", "0001011",
+];
+// The first digit is encoded by the parity of the left half.
+const EAN13_PARITIES: [&str; 10] = [
+    "LLLLLL", "LLGLGG", "LLGGLG", "LLGGGL", "LGLLGG", "LGGLLG", "LGGGLL", "LGLGLG", "LGLGGL",
+    "LGGLGL",
+];
+const EAN13_QUIET_ZONE: (usize, usize) = (11, 7);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BarcodeType {
+    Code128,
+    Ean13,
+}
+
+impl FromStr for BarcodeType {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "code128" => Ok(Self::Code128),
+            "ean13" => Ok(Self::Ean13),
+            _ => Err(format!("Unknown barcode type: {}", input)),
+        }
+    }
+}
+
+fn to_modules(pattern: &str, modules: &mut Vec<bool>) {
+    for (index, width) in pattern.chars().filter_map(|c| c.to_digit(10)).enumerate() {
+        for _ in 0..width {
+            modules.push(index % 2 == 0);
+        }
+    }
+}
+
+// Digits are packed in pairs with the code set C, and others use the code set B.
+fn encode_code128(data: &str) -> Option<Vec<bool>> {
+    let is_numeric =
+        !data.is_empty() && data.len() % 2 == 0 && data.bytes().all(|b| b.is_ascii_digit());
+    let (start, values) = if is_numeric {
+        let values = data
+            .as_bytes()
+            .chunks(2)
+            .map(|pair| usize::from((pair[0] - b'0') * 10 + (pair[1] - b'0')))
+            .collect::<Vec<_>>();
+        (CODE128_START_C, values)
+    } else {
+        let values = data
+            .chars()
+            .map(|c| match c {
+                ' '..='~' => Some(c as usize - ' ' as usize),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+        (CODE128_START_B, values)
+    };
+    if values.is_empty() {
+        return None;
+    }
+    let checksum = values
+        .iter()
+        .enumerate()
+        .fold(start, |sum, (index, value)| sum + value * (index + 1))
+        % 103;
+
+    let mut modules = vec![false; CODE128_QUIET_ZONE];
+    to_modules(CODE128_PATTERNS[start], &mut modules);
+    for value in values {
+        to_modules(CODE128_PATTERNS[value], &mut modules);
+    }
+    to_modules(CODE128_PATTERNS[checksum], &mut modules);
+    to_modules(CODE128_PATTERNS[CODE128_STOP], &mut modules);
+    modules.extend(vec![false; CODE128_QUIET_ZONE]);
+    Some(modules)
+}
+
+fn get_ean13_check_digit(digits: &[u32]) -> u32 {
+    let sum: u32 = digits
+        .iter()
+        .enumerate()
+        .map(|(index, digit)| if index % 2 == 0 { *digit } else { digit * 3 })
+        .sum();
+    (10 - sum % 10) % 10
+}
+
+// The data should have 12 digits, or 13 digits including the check digit.
+fn encode_ean13(data: &str) -> Option<(Vec<bool>, String)> {
+    let mut digits = data
+        .chars()
+        .map(|c| c.to_digit(10))
+        .collect::<Option<Vec<_>>>()?;
+    let check_digit = get_ean13_check_digit(digits.get(..12)?);
+    match digits.len() {
+        12 => digits.push(check_digit),
+        13 if digits[12] == check_digit => {}
+        _ => return None,
+    }
+
+    let bits = |code: &str, modules: &mut Vec<bool>| {
+        modules.extend(code.chars().map(|c| c == '1'));
+    };
+    let mut modules = vec![false; EAN13_QUIET_ZONE.0];
+    bits("101", &mut modules);
+    let parity = EAN13_PARITIES[digits[0] as usize];
+    for (digit, parity) in digits[1..7].iter().zip(parity.chars()) {
+        let code = EAN13_L_CODES[*digit as usize];
+        let code: String = if parity == 'G' {
+            code.chars()
+                .rev()
+                .map(|c| if c == '1' { '0' } else { '1' })
+                .collect()
+        } else {
+            code.to_string()
+        };
+        bits(&code, &mut modules);
+    }
+    bits("01010", &mut modules);
+    for digit in &digits[7..] {
+        let code: String = EAN13_L_CODES[*digit as usize]
+            .chars()
+            .map(|c| if c == '1' { '0' } else { '1' })
+            .collect();
+        bits(&code, &mut modules);
+    }
+    bits("101", &mut modules);
+    modules.extend(vec![false; EAN13_QUIET_ZONE.1]);
+
+    let text = digits.iter().map(|digit| digit.to_string()).collect();
+    Some((modules, text))
+}
+
+pub struct Barcode<'a> {
+    node: &'a LayoutNode<'a>,
+    layout_tree: &'a LayoutTree<'a>,
+    assets: &'a Assets<'a>,
+}
+
+impl<'a> Barcode<'a> {
+    pub fn new(
+        node: &'a LayoutNode,
+        layout_tree: &'a LayoutTree,
+        assets: &'a Assets<'a>,
+    ) -> Result<Self, Error> {
+        validate_name(node.element, "Barcode")?;
+        Ok(Self {
+            node,
+            layout_tree,
+            assets,
+        })
+    }
+
+    pub fn build(
+        &self,
+        pdf_page: &PdfPageReference,
+        parent_rect: Option<&Rect>,
+        clip: &Clip,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let rect = self
+            .layout_tree
+            .build_absolute_rect(self.node, parent_rect)?;
+        let rect = self.layout_tree.build_bottom_left_base_rect(&rect)?;
+        self.draw(pdf_page, &rect, clip)?;
+        Ok(())
+    }
+
+    fn encode(&self) -> Result<(Vec<bool>, String), Error> {
+        let element = self.node.element;
+        let invalid =
+            |key: &str| Error::InvalidAttribute(element.name.to_string(), key.to_string());
+        let barcode_type: BarcodeType = get_required_attr(element, "type")?;
+        let data: String = get_required_attr(element, "data")?;
+        match barcode_type {
+            BarcodeType::Code128 => encode_code128(&data)
+                .map(|modules| (modules, data.to_string()))
+                .ok_or_else(|| invalid("data")),
+            BarcodeType::Ean13 => encode_ean13(&data).ok_or_else(|| invalid("data")),
+        }
+    }
+
+    // The rect is based on the bottom-left same as printpdf.
+    fn draw(&self, pdf_page: &PdfPageReference, rect: &Rect, clip: &Clip) -> Result<(), Error> {
+        let (modules, text) = self.encode()?;
+        let style = self.node.style;
+        let pdf_layer = clip.add_layer(pdf_page);
+        let color = get_fill_color(style.fill_color);
+
+        // The human-readable text is drawn below the bars.
+        let mut bar_rect = rect.clone();
+        if get_attr(self.node.element, "text").unwrap_or(false) {
+            let font = self
+                .assets
+                .fonts
+                .get_fallbacks(style.font_family, style.font_weight, style.font_style)
+                .into_iter()
+                .next()
+                .ok_or_else(|| Error::FontAssetNotFound(style.font_family.to_string()))?;
+            let text_height = to_mm_size(style.font_size);
+            let text_width = get_text_width(font, &text, style.font_size, &[]);
+            pdf_layer.set_fill_color(color.clone());
+            use_text(
+                &pdf_layer,
+                font,
+                &text,
+                style.font_size,
+                &[],
+                (Mm(rect.x + (rect.width - text_width) / 2.0), Mm(rect.y)),
+            )?;
+            bar_rect = Rect::new(
+                rect.x,
+                rect.y + text_height,
+                rect.width,
+                (rect.height - text_height).max(0.0),
+            );
+        }
+
+        let module_width = bar_rect.width / modules.len() as f64;
+        let bars: Vec<Rect> = modules
+            .iter()
+            .enumerate()
+            .filter(|(_, is_bar)| **is_bar)
+            .map(|(index, _)| {
+                Rect::new(
+                    bar_rect.x + module_width * index as f64,
+                    bar_rect.y,
+                    module_width,
+                    bar_rect.height,
+                )
+            })
+            .collect();
+        fill_rects(&pdf_layer, &bars, color);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_string(modules: &[bool]) -> String {
+        modules.iter().map(|m| if *m { '1' } else { '0' }).collect()
+    }
+
+    #[test]
+    fn it_can_encode_code128_with_code_set_b() {
+        let modules = encode_code128("A").unwrap();
+        // Quiet zone, start B, "A", checksum (104 + 33) % 103 = 34, stop and quiet zone.
+        assert_eq!(modules.len(), 10 + 11 * 3 + 13 + 10);
+        assert_eq!(to_string(&modules[10..21]), "11010010000");
+    }
+
+    #[test]
+    fn it_can_encode_code128_with_code_set_c() {
+        let modules = encode_code128("1234").unwrap();
+        assert_eq!(modules.len(), 10 + 11 * 4 + 13 + 10);
+        assert_eq!(to_string(&modules[10..21]), "11010011100");
+    }
+
+    #[test]
+    fn it_cannot_encode_code128_with_non_ascii() {
+        assert!(encode_code128("テスト").is_none());
+    }
+
+    #[test]
+    fn it_can_encode_ean13_with_check_digit() {
+        let (modules, text) = encode_ean13("400638133393").unwrap();
+        assert_eq!(text, "4006381333931");
+        assert_eq!(modules.len(), 11 + 95 + 7);
+    }
+
+    #[test]
+    fn it_cannot_encode_ean13_with_wrong_check_digit() {
+        assert!(encode_ean13("4006381333932").is_none());
+        assert!(encode_ean13("40063813339").is_none());
+    }
+}
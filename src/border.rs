@@ -1,13 +1,14 @@
 use printpdf::{Color, PdfPageReference};
 use taffy::style::Dimension;
 
-use crate::rect::Rect;
+use crate::{clip::Clip, rect::Rect};
 
 pub struct Border<'a> {
     pub border: &'a taffy::geometry::Rect<Dimension>,
     pub border_color: &'a Color,
     pub rect: &'a Rect,
     pub pdf_page: &'a PdfPageReference,
+    pub clip: &'a Clip,
 }
 
 impl<'a> Border<'a> {
@@ -57,7 +58,7 @@ impl<'a> Border<'a> {
     }
 
     pub fn draw_border(&self, rect: Rect) {
-        let pdf_layer = self.pdf_page.add_layer("");
+        let pdf_layer = self.clip.add_layer(self.pdf_page);
         pdf_layer.set_fill_color(self.border_color.clone());
         pdf_layer.set_outline_color(self.border_color.clone());
         pdf_layer.set_outline_thickness(0.0);
@@ -0,0 +1,492 @@
+use std::{f64::consts::PI, str::FromStr};
+
+use printpdf::{Color, LineCapStyle, LineJoinStyle, Mm, PdfLayerReference, PdfPageReference};
+
+use crate::{
+    assets::{Assets, FontAssetItem},
+    clip::Clip,
+    dom::{get_attr, get_required_attr, validate_name, Children, Element},
+    error::Error,
+    hex_color::HexColor,
+    layout::{LayoutNode, LayoutTree},
+    path::{fill_rects, get_fill_color, paint_operation, FillRule, Path, Stroke},
+    rect::Rect,
+    text::{get_text_width, to_mm_size, use_text},
+};
+
+// Colors of series when the `color` attribute is not set.
+const PALETTE: [&str; 8] = [
+    "#4e79a7", "#f28e2b", "#e15759", "#76b7b2", "#59a14f", "#edc948", "#b07aa1", "#ff9da7",
+];
+const TICK_COUNT: f64 = 5.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChartType {
+    Bar,
+    Line,
+    Pie,
+}
+
+impl FromStr for ChartType {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "bar" => Ok(Self::Bar),
+            "line" => Ok(Self::Line),
+            "pie" => Ok(Self::Pie),
+            _ => Err(format!("Unknown chart type: {}", input)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Point {
+    label: String,
+    value: f64,
+    color: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+struct Series {
+    name: String,
+    color: String,
+    points: Vec<Point>,
+}
+
+// Ticks are rounded to 1, 2 or 5 times a power of 10.
+fn get_ticks(min: f64, max: f64) -> Vec<f64> {
+    let (min, max) = if min == max {
+        (min, min + 1.0)
+    } else {
+        (min, max)
+    };
+    let rough_step = (max - min) / TICK_COUNT;
+    let magnitude = 10_f64.powf(rough_step.log10().floor());
+    let step = [1.0, 2.0, 5.0, 10.0]
+        .iter()
+        .map(|factor| factor * magnitude)
+        .find(|step| *step >= rough_step)
+        .unwrap_or(10.0 * magnitude);
+    let first = (min / step).floor() as i64;
+    let last = (max / step).ceil() as i64;
+    (first..=last).map(|index| index as f64 * step).collect()
+}
+
+fn format_tick(value: f64, step: f64) -> String {
+    let decimals = (-step.log10().floor()).max(0.0) as usize;
+    format!("{:.*}", decimals, value)
+}
+
+pub struct Chart<'a> {
+    node: &'a LayoutNode<'a>,
+    layout_tree: &'a LayoutTree<'a>,
+    assets: &'a Assets<'a>,
+}
+
+impl<'a> Chart<'a> {
+    pub fn new(
+        node: &'a LayoutNode,
+        layout_tree: &'a LayoutTree,
+        assets: &'a Assets<'a>,
+    ) -> Result<Self, Error> {
+        validate_name(node.element, "Chart")?;
+        Ok(Self {
+            node,
+            layout_tree,
+            assets,
+        })
+    }
+
+    pub fn build(
+        &self,
+        pdf_page: &PdfPageReference,
+        parent_rect: Option<&Rect>,
+        clip: &Clip,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let rect = self
+            .layout_tree
+            .build_absolute_rect(self.node, parent_rect)?;
+        let rect = self.layout_tree.build_bottom_left_base_rect(&rect)?;
+        self.draw(pdf_page, &rect, clip)?;
+        Ok(())
+    }
+
+    fn load_font(&self) -> Result<&FontAssetItem<'a>, Error> {
+        let style = self.node.style;
+        self.assets
+            .fonts
+            .get_fallbacks(style.font_family, style.font_weight, style.font_style)
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::FontAssetNotFound(style.font_family.to_string()))
+    }
+
+    fn invalid_attribute(element: &Element, key: &str) -> Error {
+        Error::InvalidAttribute(element.name.to_string(), key.to_string())
+    }
+
+    // Values such as `inf` and `NaN` are rejected because they can't be scaled to the chart.
+    fn parse_value(element: &Element, key: &str, value: &str) -> Result<f64, Error> {
+        value
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|value| value.is_finite())
+            .ok_or_else(|| Self::invalid_attribute(element, key))
+    }
+
+    // Points are given by `<Point label="..." value="...">` children,
+    // or by the `values` attribute with the `labels` attribute of the chart.
+    fn parse_series(&self) -> Result<Vec<Series>, Error> {
+        let element = self.node.element;
+        let labels: Vec<String> = get_attr::<String>(element, "labels")
+            .map(|labels| labels.split(',').map(|l| l.trim().to_string()).collect())
+            .unwrap_or_default();
+        let children: Vec<&Element> = match &element.children {
+            Children::Elements(children) => children.iter().collect(),
+            Children::Text(_) | Children::Inline(_) => vec![],
+        };
+
+        children
+            .into_iter()
+            .enumerate()
+            .map(|(index, child)| {
+                validate_name(child, "Series")?;
+                let points = match get_attr::<String>(child, "values") {
+                    Some(values) => values
+                        .split(',')
+                        .enumerate()
+                        .map(|(index, value)| {
+                            Ok(Point {
+                                label: labels.get(index).cloned().unwrap_or_default(),
+                                value: Self::parse_value(child, "values", value)?,
+                                color: None,
+                            })
+                        })
+                        .collect::<Result<Vec<_>, Error>>()?,
+                    None => self.parse_points(child)?,
+                };
+                Ok(Series {
+                    name: get_attr(child, "name").unwrap_or_default(),
+                    color: get_attr(child, "color")
+                        .unwrap_or_else(|| PALETTE[index % PALETTE.len()].to_string()),
+                    points,
+                })
+            })
+            .collect()
+    }
+
+    fn parse_points(&self, series: &Element) -> Result<Vec<Point>, Error> {
+        match &series.children {
+            Children::Elements(children) => children
+                .iter()
+                .map(|child| {
+                    validate_name(child, "Point")?;
+                    Ok(Point {
+                        label: get_attr(child, "label").unwrap_or_default(),
+                        value: Self::parse_value(
+                            child,
+                            "value",
+                            &get_required_attr::<String>(child, "value")?,
+                        )?,
+                        color: get_attr(child, "color"),
+                    })
+                })
+                .collect(),
+            Children::Text(_) | Children::Inline(_) => Ok(vec![]),
+        }
+    }
+
+    fn build_stroke(&self, color: &str, width: f64) -> Stroke {
+        Stroke {
+            color: HexColor::new(color).into(),
+            width,
+            cap: LineCapStyle::Butt,
+            join: LineJoinStyle::Round,
+            dash: None,
+        }
+    }
+
+    fn draw_line(&self, pdf_layer: &PdfLayerReference, points: &[(f64, f64)], stroke: &Stroke) {
+        stroke.apply(pdf_layer, 1.0);
+        Path::from_polygon(points, false).add_to_layer(pdf_layer);
+        pdf_layer.add_operation(paint_operation(None, true));
+    }
+
+    fn draw_text(
+        &self,
+        pdf_layer: &PdfLayerReference,
+        font: &FontAssetItem,
+        text: &str,
+        (x, y): (f64, f64),
+    ) -> Result<(), Error> {
+        let font_size = self.node.style.font_size;
+        use_text(pdf_layer, font, text, font_size, &[], (Mm(x), Mm(y)))
+    }
+
+    fn get_axis_color(&self) -> &str {
+        self.node.style.stroke_color.unwrap_or("#000000")
+    }
+
+    fn draw(&self, pdf_page: &PdfPageReference, rect: &Rect, clip: &Clip) -> Result<(), Error> {
+        let element = self.node.element;
+        let chart_type: ChartType = get_required_attr(element, "type")?;
+        let series = self.parse_series()?;
+        let font = self.load_font()?;
+        let pdf_layer = clip.add_layer(pdf_page);
+
+        let legend: Vec<(String, String)> = match chart_type {
+            ChartType::Pie => series
+                .first()
+                .map(|series| {
+                    series
+                        .points
+                        .iter()
+                        .enumerate()
+                        .map(|(index, point)| {
+                            (point.label.clone(), Self::get_slice_color(point, index))
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+            _ => series
+                .iter()
+                .map(|series| (series.name.clone(), series.color.clone()))
+                .collect(),
+        };
+        let legend_height = self.draw_legend(&pdf_layer, font, rect, &legend)?;
+        let plot_rect = Rect::new(
+            rect.x,
+            rect.y + legend_height,
+            rect.width,
+            (rect.height - legend_height).max(0.0),
+        );
+
+        match chart_type {
+            ChartType::Pie => {
+                self.draw_pie(&pdf_layer, &plot_rect, series.first());
+                Ok(())
+            }
+            _ => self.draw_axis_chart(&pdf_layer, font, &plot_rect, &series, chart_type),
+        }
+    }
+
+    fn get_slice_color(point: &Point, index: usize) -> String {
+        point
+            .color
+            .clone()
+            .unwrap_or_else(|| PALETTE[index % PALETTE.len()].to_string())
+    }
+
+    // Legend items are drawn in a row at the bottom of the chart,
+    // and it returns the height of the legend.
+    fn draw_legend(
+        &self,
+        pdf_layer: &PdfLayerReference,
+        font: &FontAssetItem,
+        rect: &Rect,
+        items: &[(String, String)],
+    ) -> Result<f64, Error> {
+        let font_size = self.node.style.font_size;
+        let text_height = to_mm_size(font_size);
+        if items.iter().all(|(name, _)| name.is_empty()) {
+            return Ok(0.0);
+        }
+        let gap = text_height / 2.0;
+        let y = rect.y + gap;
+        let mut x = rect.x + gap;
+        for (name, color) in items {
+            let swatch = Rect::new(x, y, text_height * 0.8, text_height * 0.8);
+            fill_rects(pdf_layer, &[swatch], HexColor::new(color).into());
+            x += text_height + gap / 2.0;
+            pdf_layer.set_fill_color(get_fill_color(Some(self.get_axis_color())));
+            self.draw_text(pdf_layer, font, name, (x, y))?;
+            x += get_text_width(font, name, font_size, &[]) + gap * 2.0;
+        }
+        Ok(text_height * 2.0)
+    }
+
+    fn draw_pie(&self, pdf_layer: &PdfLayerReference, rect: &Rect, series: Option<&Series>) {
+        let points = match series {
+            Some(series) => &series.points,
+            None => return,
+        };
+        let total: f64 = points.iter().map(|point| point.value.max(0.0)).sum();
+        if total <= 0.0 {
+            return;
+        }
+        let radius = rect.width.min(rect.height) / 2.0 * 0.9;
+        let (cx, cy) = (rect.x + rect.width / 2.0, rect.y + rect.height / 2.0);
+
+        // Slices start from the top and go clockwise.
+        let mut angle = PI / 2.0;
+        for (index, point) in points.iter().enumerate() {
+            let sweep = point.value.max(0.0) / total * 2.0 * PI;
+            let color: Color = HexColor::new(&Self::get_slice_color(point, index)).into();
+            pdf_layer.set_fill_color(color);
+            Path::from_pie_slice(cx, cy, radius, angle, angle - sweep).add_to_layer(pdf_layer);
+            pdf_layer.add_operation(paint_operation(Some(FillRule::NonZero), false));
+            angle -= sweep;
+        }
+    }
+
+    fn draw_axis_chart(
+        &self,
+        pdf_layer: &PdfLayerReference,
+        font: &FontAssetItem,
+        rect: &Rect,
+        series: &[Series],
+        chart_type: ChartType,
+    ) -> Result<(), Error> {
+        let style = self.node.style;
+        let font_size = style.font_size;
+        let text_height = to_mm_size(font_size);
+        let gap = text_height / 2.0;
+
+        let values = series
+            .iter()
+            .flat_map(|series| series.points.iter().map(|p| p.value));
+        let min = values.clone().fold(0.0_f64, f64::min);
+        let max = values.fold(0.0_f64, f64::max);
+        let ticks = get_ticks(min, max);
+        let step = ticks.get(1).zip(ticks.first()).map_or(1.0, |(b, a)| b - a);
+        let (low, high) = (ticks[0], ticks[ticks.len() - 1]);
+        let tick_labels: Vec<String> = ticks.iter().map(|tick| format_tick(*tick, step)).collect();
+        let label_width = tick_labels
+            .iter()
+            .map(|label| get_text_width(font, label, font_size, &[]))
+            .fold(0.0, f64::max);
+
+        let plot = Rect::new(
+            rect.x + label_width + gap * 2.0,
+            rect.y + text_height + gap * 2.0,
+            (rect.width - label_width - gap * 3.0).max(0.0),
+            (rect.height - text_height - gap * 3.0).max(0.0),
+        );
+        let to_y = |value: f64| plot.y + (value - low) / (high - low) * plot.height;
+
+        // Axes, ticks and labels.
+        let axis_color = self.get_axis_color();
+        let axis = self.build_stroke(axis_color, style.stroke_width);
+        pdf_layer.set_fill_color(get_fill_color(Some(axis_color)));
+        for (tick, label) in ticks.iter().zip(&tick_labels) {
+            let y = to_y(*tick);
+            self.draw_line(pdf_layer, &[(plot.x - gap / 2.0, y), (plot.x, y)], &axis);
+            let width = get_text_width(font, label, font_size, &[]);
+            self.draw_text(
+                pdf_layer,
+                font,
+                label,
+                (plot.x - gap - width, y - text_height / 3.0),
+            )?;
+        }
+        self.draw_line(
+            pdf_layer,
+            &[(plot.x, plot.y), (plot.x, plot.y + plot.height)],
+            &axis,
+        );
+        self.draw_line(
+            pdf_layer,
+            &[(plot.x, to_y(0.0)), (plot.right(), to_y(0.0))],
+            &axis,
+        );
+
+        let categories = series.iter().map(|s| s.points.len()).max().unwrap_or(0);
+        if categories == 0 {
+            return Ok(());
+        }
+        let group_width = plot.width / categories as f64;
+        let labels = series
+            .iter()
+            .max_by_key(|series| series.points.len())
+            .map(|series| &series.points);
+        for (index, point) in labels.into_iter().flatten().enumerate() {
+            let width = get_text_width(font, &point.label, font_size, &[]);
+            let x = plot.x + group_width * (index as f64 + 0.5) - width / 2.0;
+            self.draw_text(pdf_layer, font, &point.label, (x, rect.y + gap))?;
+        }
+
+        match chart_type {
+            ChartType::Bar => {
+                let bar_width = group_width * 0.8 / series.len() as f64;
+                for (series_index, series) in series.iter().enumerate() {
+                    let bars: Vec<Rect> = series
+                        .points
+                        .iter()
+                        .enumerate()
+                        .map(|(index, point)| {
+                            let (from, to) = (to_y(0.0), to_y(point.value));
+                            Rect::new(
+                                plot.x
+                                    + group_width * (index as f64 + 0.1)
+                                    + bar_width * series_index as f64,
+                                from.min(to),
+                                bar_width,
+                                (to - from).abs(),
+                            )
+                        })
+                        .collect();
+                    fill_rects(pdf_layer, &bars, HexColor::new(&series.color).into());
+                }
+            }
+            _ => {
+                for series in series {
+                    let points: Vec<(f64, f64)> = series
+                        .points
+                        .iter()
+                        .enumerate()
+                        .map(|(index, point)| {
+                            (
+                                plot.x + group_width * (index as f64 + 0.5),
+                                to_y(point.value),
+                            )
+                        })
+                        .collect();
+                    let stroke = self.build_stroke(&series.color, style.stroke_width * 2.0);
+                    self.draw_line(pdf_layer, &points, &stroke);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dom;
+
+    #[test]
+    fn it_makes_ticks_with_round_steps() {
+        assert_eq!(get_ticks(0.0, 10.0), vec![0.0, 2.0, 4.0, 6.0, 8.0, 10.0]);
+        assert_eq!(get_ticks(0.0, 73.0), vec![0.0, 20.0, 40.0, 60.0, 80.0]);
+        assert_eq!(get_ticks(-3.0, 4.0), vec![-4.0, -2.0, 0.0, 2.0, 4.0]);
+    }
+
+    #[test]
+    fn it_makes_ticks_when_all_values_are_same() {
+        let ticks = get_ticks(0.0, 0.0);
+        assert_eq!(ticks.len(), 6);
+        assert_eq!(ticks.first(), Some(&0.0));
+        assert_eq!(ticks.last(), Some(&1.0));
+    }
+
+    #[test]
+    fn it_formats_ticks_by_the_step() {
+        assert_eq!(format_tick(20.0, 20.0), "20");
+        assert_eq!(format_tick(3.0 * 0.2, 0.2), "0.6");
+    }
+
+    #[test]
+    fn it_rejects_values_that_are_not_finite() {
+        let element = dom::parse(r#"<Point value="inf" />"#).unwrap();
+        assert_eq!(
+            Chart::parse_value(&element, "value", " 1.5").ok(),
+            Some(1.5)
+        );
+        assert!(Chart::parse_value(&element, "value", "inf").is_err());
+        assert!(Chart::parse_value(&element, "value", "-infinity").is_err());
+        assert!(Chart::parse_value(&element, "value", "NaN").is_err());
+    }
+}
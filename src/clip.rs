@@ -0,0 +1,60 @@
+use printpdf::{Line, PdfLayerReference, PdfPageReference};
+
+use crate::rect::Rect;
+
+// Printpdf wraps every layer in its own graphics state,
+// so a clipping path only affects the layer it was added to.
+// We keep the paths of all clipping ancestors and add them again
+// to each layer drawn by their descendants.
+#[derive(Debug, Clone, Default)]
+pub struct Clip {
+    paths: Vec<Line>,
+}
+
+impl Clip {
+    pub fn push(&self, rect: &Rect, radius: f64) -> Self {
+        let mut paths = self.paths.clone();
+        paths.push(rect.to_clipping_line(radius));
+        Self { paths }
+    }
+
+    pub fn add_layer(&self, pdf_page: &PdfPageReference) -> PdfLayerReference {
+        let pdf_layer = pdf_page.add_layer("");
+        for path in &self.paths {
+            pdf_layer.add_shape(path.clone());
+        }
+        pdf_layer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lopdf::content::Content;
+    use printpdf::{Mm, PdfDocument};
+
+    use super::*;
+
+    #[test]
+    fn it_keeps_clipping_paths_of_ancestors() {
+        let (pdf_doc, page_index, _) = PdfDocument::new("", Mm(100.0), Mm(100.0), "");
+        let pdf_page = pdf_doc.get_page(page_index);
+        let parent = Clip::default().push(&Rect::new(0.0, 0.0, 80.0, 80.0), 5.0);
+        let child = parent.push(&Rect::new(10.0, 10.0, 20.0, 20.0), 0.0);
+        child.add_layer(&pdf_page);
+        assert_eq!(parent.paths.len(), 1);
+
+        let doc = lopdf::Document::load_mem(&pdf_doc.save_to_bytes().unwrap()).unwrap();
+        let page_id = doc.get_pages()[&1];
+        let content = Content::decode(&doc.get_page_content(page_id).unwrap()).unwrap();
+        let operators: Vec<&str> = content
+            .operations
+            .iter()
+            .map(|operation| operation.operator.as_str())
+            .collect();
+        let clipping_paths: Vec<&[&str]> = operators.split(|operator| *operator == "W").collect();
+        // The rounded path of the parent comes first, and then the sharp one of the child.
+        assert_eq!(clipping_paths.len(), 3);
+        assert!(clipping_paths[0].contains(&"c"));
+        assert!(!clipping_paths[1].contains(&"c"));
+    }
+}
@@ -4,6 +4,8 @@ use crate::{
     assets::Assets,
     dom::{get_attr, validate_name, Children, Element},
     error::Error,
+    list::ListCounters,
+    output::Output,
     page::Page,
 };
 
@@ -19,17 +21,24 @@ impl<'a> Document<'a> {
         Ok(Self { root, assets })
     }
 
-    pub fn build(&mut self) -> Result<PdfDocumentReference, Box<dyn std::error::Error>> {
+    // Images and fonts are optimized by `Output` when it's saved.
+    pub fn build(&mut self) -> Result<Output, Box<dyn std::error::Error>> {
         let pdf_doc = self.build_pdf_doc();
-        self.assets.prepare(&pdf_doc)?;
+        self.assets.prepare()?;
+        // Lists are numbered from the start again when the document is built again.
+        let lists = ListCounters::default();
 
         if let Children::Elements(children) = &self.root.children {
             for element in children {
                 let page = Page::new(element, &self.assets)?;
-                page.build(&pdf_doc)?;
+                page.build(&pdf_doc, &lists)?;
             }
         }
-        Ok(pdf_doc)
+        Ok(Output::new(
+            pdf_doc,
+            self.assets.images.to_embedded_images(),
+            self.assets.fonts.to_embedded_fonts(),
+        ))
     }
 
     fn build_pdf_doc(&self) -> PdfDocumentReference {
@@ -2,9 +2,9 @@ use std::collections::HashMap;
 
 use nom::{
     branch::alt,
-    bytes::complete::{tag, take_till, take_while},
+    bytes::complete::{tag, take_till, take_till1},
     character::complete::{alphanumeric1, char, multispace0, multispace1},
-    character::{is_alphabetic, is_alphanumeric},
+    character::is_alphabetic,
     combinator::{all_consuming, map, opt, verify},
     multi::{many1, separated_list0},
     sequence::{delimited, pair, preceded, separated_pair},
@@ -17,6 +17,14 @@ use crate::error::Error;
 pub enum Children {
     Elements(Vec<Element>),
     Text(String),
+    // Texts mixed with inline elements such as `<Ruby>`.
+    Inline(Vec<InlineChild>),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum InlineChild {
+    Element(Element),
+    Text(String),
 }
 
 #[derive(Debug, PartialEq)]
@@ -35,6 +43,21 @@ pub fn get_required_attr<T: std::str::FromStr>(element: &Element, key: &str) ->
         .ok_or_else(|| Error::RequiredAttribute(element.name.to_string(), key.to_string()))
 }
 
+// Same as `get_required_attr`, but the reason is given if the value can't be parsed.
+pub fn parse_required_attr<T>(element: &Element, key: &str) -> Result<T, Error>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    let value = element
+        .attributes
+        .get(key)
+        .ok_or_else(|| Error::RequiredAttribute(element.name.to_string(), key.to_string()))?;
+    value.parse().map_err(|err: T::Err| {
+        Error::InvalidAttributeValue(element.name.to_string(), key.to_string(), err.to_string())
+    })
+}
+
 pub fn validate_name(element: &Element, expected_name: &str) -> Result<(), Error> {
     if element.name == expected_name {
         Ok(())
@@ -58,7 +81,7 @@ fn tag_name(input: &str) -> IResult<&str, &str> {
 }
 
 fn attribute_value(input: &str) -> IResult<&str, &str> {
-    take_while(|chr: char| is_alphanumeric(chr as u8) || chr == '-')(input)
+    take_till(|chr: char| chr == '"' || chr == '<')(input)
 }
 
 fn quoted_attribute_value(input: &str) -> IResult<&str, &str> {
@@ -87,43 +110,75 @@ fn tag_name_with_attributes(input: &str) -> IResult<&str, Element> {
     })(input)
 }
 
+// Whitespaces around inline elements are kept as texts.
+fn inline_child(input: &str) -> IResult<&str, InlineChild> {
+    let element = map(alt((single_element, parent_element)), InlineChild::Element);
+    let text = map(take_till1(|c| c == '<'), |s: &str| {
+        InlineChild::Text(s.to_string())
+    });
+    alt((element, text))(input)
+}
+
+fn has_text_and_elements(children: &[InlineChild]) -> bool {
+    let has_text = children.iter().any(|child| match child {
+        InlineChild::Text(text) => !text.trim().is_empty(),
+        InlineChild::Element(_) => false,
+    });
+    let has_element = children
+        .iter()
+        .any(|child| matches!(child, InlineChild::Element(_)));
+    has_text && has_element
+}
+
+// Whitespaces between elements are ignored unless there are texts.
+fn build_children(children: Vec<InlineChild>) -> Children {
+    if has_text_and_elements(&children) {
+        return Children::Inline(children);
+    }
+    let mut elements = Vec::new();
+    let mut text = String::new();
+    for child in children {
+        match child {
+            InlineChild::Element(element) => elements.push(element),
+            InlineChild::Text(child) => text.push_str(&child),
+        }
+    }
+    if elements.is_empty() {
+        Children::Text(text)
+    } else {
+        Children::Elements(elements)
+    }
+}
+
 fn child_elements(input: &str) -> IResult<&str, Children> {
-    let elements = map(many1(element), Children::Elements);
+    let children = map(many1(inline_child), build_children);
     let text = map(take_till(|c| c == '<'), |s: &str| {
         Children::Text(s.to_string())
     });
-    alt((elements, text))(input)
+    alt((children, text))(input)
 }
 
 fn parent_element(input: &str) -> IResult<&str, Element> {
-    let (input, mut parent) = trimmed(delimited(
-        char('<'),
-        trimmed(tag_name_with_attributes),
-        char('>'),
-    ))(input)?;
+    let (input, mut parent) =
+        delimited(char('<'), trimmed(tag_name_with_attributes), char('>'))(input)?;
 
     let (input, children) = child_elements(input)?;
     parent.children = children;
 
-    let (input, _) = trimmed(delimited(
-        tag("</"),
-        trimmed(tag(&parent.name[..])),
-        char('>'),
-    ))(input)?;
+    let (input, _) = preceded(
+        multispace0,
+        delimited(tag("</"), trimmed(tag(&parent.name[..])), char('>')),
+    )(input)?;
 
     Ok((input, parent))
 }
 
 fn single_element(input: &str) -> IResult<&str, Element> {
-    trimmed(delimited(
-        char('<'),
-        trimmed(tag_name_with_attributes),
-        tag("/>"),
-    ))(input)
+    delimited(char('<'), trimmed(tag_name_with_attributes), tag("/>"))(input)
 }
 
 fn element(input: &str) -> IResult<&str, Element> {
-    alt((single_element, parent_element))(input)
+    trimmed(alt((single_element, parent_element)))(input)
 }
 
 pub fn parse(input: &str) -> Result<Element, nom::Err<nom::error::Error<&str>>> {
@@ -222,6 +277,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn it_can_parse_when_attributes_have_symbols_and_spaces() {
+        assert_eq!(
+            parse("<A d=\"M0,0 L10.5 -2 Z\" />").unwrap(),
+            Element {
+                name: String::from("A"),
+                attributes: [(String::from("d"), String::from("M0,0 L10.5 -2 Z"))]
+                    .into_iter()
+                    .collect(),
+                children: Children::Text("".to_string())
+            }
+        );
+    }
+
+    #[test]
+    fn it_cannot_parse_when_attributes_have_unclosed_quote() {
+        assert!(parse("<A a=\"b />").is_err());
+    }
+
     #[test]
     fn it_can_parse_when_element_has_children() {
         assert_eq!(
@@ -270,6 +344,18 @@ mod tests {
         );
     }
 
+    #[test]
+    fn it_keeps_whitespaces_of_text() {
+        assert_eq!(
+            parse("<A>\n  text\n</A>").unwrap(),
+            Element {
+                name: String::from("A"),
+                attributes: HashMap::new(),
+                children: Children::Text("\n  text\n".to_string()),
+            }
+        );
+    }
+
     #[test]
     fn it_can_parse_when_child_is_multi_byte_text() {
         assert_eq!(
@@ -298,8 +384,65 @@ mod tests {
     }
 
     #[test]
-    fn it_cannot_parse_when_child_has_text_and_tags() {
-        assert!(parse("<A>text<B></B></A>").is_err());
+    fn it_can_parse_when_child_has_text_and_tags() {
+        assert_eq!(
+            parse("<A>text <B /> text</A>").unwrap(),
+            Element {
+                name: String::from("A"),
+                attributes: HashMap::new(),
+                children: Children::Inline(vec![
+                    InlineChild::Text("text ".to_string()),
+                    InlineChild::Element(Element {
+                        name: String::from("B"),
+                        attributes: HashMap::new(),
+                        children: Children::Text("".to_string()),
+                    }),
+                    InlineChild::Text(" text".to_string()),
+                ]),
+            }
+        );
+    }
+
+    #[test]
+    fn it_can_parse_when_inline_element_has_text() {
+        assert_eq!(
+            parse("<A>text<B>b</B></A>").unwrap(),
+            Element {
+                name: String::from("A"),
+                attributes: HashMap::new(),
+                children: Children::Inline(vec![
+                    InlineChild::Text("text".to_string()),
+                    InlineChild::Element(Element {
+                        name: String::from("B"),
+                        attributes: HashMap::new(),
+                        children: Children::Text("b".to_string()),
+                    }),
+                ]),
+            }
+        );
+    }
+
+    #[test]
+    fn it_can_parse_when_elements_are_separated_by_newlines() {
+        assert_eq!(
+            parse("<A>\n  <B />\n  <C />\n</A>").unwrap(),
+            Element {
+                name: String::from("A"),
+                attributes: HashMap::new(),
+                children: Children::Elements(vec![
+                    Element {
+                        name: String::from("B"),
+                        attributes: HashMap::new(),
+                        children: Children::Text("".to_string()),
+                    },
+                    Element {
+                        name: String::from("C"),
+                        attributes: HashMap::new(),
+                        children: Children::Text("".to_string()),
+                    },
+                ]),
+            }
+        );
     }
 
     #[test]
@@ -341,4 +484,16 @@ mod tests {
     fn it_cannot_parse_when_it_is_empty() {
         assert!(parse("").is_err());
     }
+
+    #[test]
+    fn it_gives_the_reason_of_invalid_attributes() {
+        let element = parse(r#"<Polygon points="0,0 10" />"#).unwrap();
+        let result: Result<f64, Error> = parse_required_attr(&element, "points");
+        assert!(matches!(
+            result,
+            Err(Error::InvalidAttributeValue(name, key, _)) if name == "Polygon" && key == "points"
+        ));
+        let result: Result<f64, Error> = parse_required_attr(&element, "d");
+        assert!(matches!(result, Err(Error::RequiredAttribute(_, _))));
+    }
 }
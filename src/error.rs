@@ -18,6 +18,18 @@ pub enum Error {
     #[error("The font of {0} is not found in assets.")]
     FontAssetNotFound(String),
 
+    #[error("The font of {0} can't be embedded.")]
+    InvalidFontAsset(String),
+
+    #[error("The font feature of {0} is invalid.")]
+    InvalidFontFeature(String),
+
     #[error("The {0} component can't have children.")]
     InvalidChildren(String),
+
+    #[error("The {1} attribute of {0} is invalid.")]
+    InvalidAttribute(String, String),
+
+    #[error("The {1} attribute of {0} is invalid: {2}")]
+    InvalidAttributeValue(String, String, String),
 }
@@ -0,0 +1,126 @@
+use std::str::FromStr;
+
+use hypher::{hyphenate, Lang};
+
+// The soft hyphen is an invisible hyphenation point in the input.
+// It's hidden by shaping, so it has no width.
+pub const SOFT_HYPHEN: char = '\u{ad}';
+
+// The language of patterns given by the `lang` attribute, e.g. `en` or `de-DE`.
+// Knuth-Liang patterns of English and German are bundled by hypher.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Language(Lang);
+
+impl FromStr for Language {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let code = input
+            .split(['-', '_'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        <[u8; 2]>::try_from(code.as_bytes())
+            .ok()
+            .and_then(Lang::from_iso)
+            .map(Self)
+            .ok_or_else(|| format!("Unsupported language: {}", input))
+    }
+}
+
+// Splits the text into words with the positions of bytes.
+fn split_words(text: &str) -> Vec<(usize, &str)> {
+    let is_word = |c: char| c.is_alphabetic() || c == SOFT_HYPHEN;
+    let mut words = Vec::new();
+    let mut from = None;
+    for (index, c) in text.char_indices() {
+        match from {
+            None if is_word(c) => from = Some(index),
+            Some(start) if !is_word(c) => {
+                words.push((start, &text[start..index]));
+                from = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(start) = from {
+        words.push((start, &text[start..]));
+    }
+    words
+}
+
+// Returns positions of bytes in ascending order where words can be hyphenated.
+// Soft hyphens are used instead of the patterns in words that have them.
+pub fn find_hyphenation_points(text: &str, language: Option<Language>) -> Vec<usize> {
+    let mut points = Vec::new();
+    for (start, word) in split_words(text) {
+        let end = start + word.len();
+        if word.contains(SOFT_HYPHEN) {
+            points.extend(
+                word.match_indices(SOFT_HYPHEN)
+                    .map(|(index, _)| start + index + SOFT_HYPHEN.len_utf8())
+                    .filter(|point| *point < end),
+            );
+        } else if let Some(Language(lang)) = language {
+            let mut point = start;
+            for syllable in hyphenate(word, lang) {
+                point += syllable.len();
+                if point < end {
+                    points.push(point);
+                }
+            }
+        }
+    }
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split_at_points<'a>(text: &'a str, points: &[usize]) -> Vec<&'a str> {
+        let mut from = 0;
+        let mut parts = Vec::new();
+        for point in points {
+            parts.push(&text[from..*point]);
+            from = *point;
+        }
+        parts.push(&text[from..]);
+        parts
+    }
+
+    #[test]
+    fn it_can_parse_language() {
+        assert_eq!("en".parse(), Ok(Language(Lang::English)));
+        assert_eq!("de-DE".parse(), Ok(Language(Lang::German)));
+        assert!("ja".parse::<Language>().is_err());
+        assert!("english".parse::<Language>().is_err());
+    }
+
+    #[test]
+    fn it_can_find_hyphenation_points_by_patterns() {
+        let text = "an extensive text";
+        let points = find_hyphenation_points(text, Some(Language(Lang::English)));
+        assert_eq!(
+            split_at_points(text, &points),
+            vec!["an ex", "ten", "sive text"]
+        );
+    }
+
+    #[test]
+    fn it_prefers_soft_hyphens_to_patterns() {
+        let text = "ex\u{ad}tensive";
+        let points = find_hyphenation_points(text, Some(Language(Lang::English)));
+        assert_eq!(split_at_points(text, &points), vec!["ex\u{ad}", "tensive"]);
+    }
+
+    #[test]
+    fn it_finds_only_soft_hyphens_without_language() {
+        let text = "extensive Silben\u{ad}trennung";
+        let points = find_hyphenation_points(text, None);
+        assert_eq!(
+            split_at_points(text, &points),
+            vec!["extensive Silben\u{ad}", "trennung"]
+        );
+    }
+}
@@ -1,11 +1,15 @@
-use printpdf::{ImageTransform, ImageXObject, Mm, PdfPageReference, Px};
+use lopdf::{content::Operation, Object};
+use printpdf::{CurTransMat, Mm, PdfPageReference, Pt};
+use taffy::style::Dimension;
 
 use crate::{
     assets::Assets,
-    dom::{get_required_attr, validate_name},
+    clip::Clip,
+    dom::{get_attr, get_required_attr, validate_name, Element},
     error::Error,
     layout::{LayoutNode, LayoutTree},
     rect::Rect,
+    style::{ObjectFit, Overflow},
 };
 
 pub struct Image<'a> {
@@ -15,7 +19,9 @@ pub struct Image<'a> {
 }
 
 impl<'a> Image<'a> {
-    const BASE_DPI: f64 = 300.0;
+    pub const BASE_DPI: f64 = 300.0;
+    // Same as CSS pixels.
+    pub const SVG_DPI: f64 = 96.0;
 
     pub fn new(
         node: &'a LayoutNode,
@@ -34,49 +40,122 @@ impl<'a> Image<'a> {
         &self,
         pdf_page: &PdfPageReference,
         parent_rect: Option<&Rect>,
+        clip: &Clip,
     ) -> Result<(), Box<dyn std::error::Error>> {
         let rect = self
             .layout_tree
             .build_absolute_rect(self.node, parent_rect)?;
-        self.draw(pdf_page, rect)?;
+        self.draw(pdf_page, rect, clip)?;
         Ok(())
     }
 
-    fn get_image(&self) -> Result<printpdf::Image, Error> {
-        let key: String = get_required_attr(self.node.element, "src")?;
-        self.assets
-            .images
-            .get(&key)
-            .ok_or_else(|| Error::ImageAssetNotFound(key.to_string()))
+    pub fn get_dpi(element: &Element, is_svg: bool) -> Result<f64, Error> {
+        let base_dpi = if is_svg {
+            Self::SVG_DPI
+        } else {
+            Self::BASE_DPI
+        };
+        match get_attr(element, "dpi").unwrap_or(base_dpi) {
+            dpi if dpi > 0.0 => Ok(dpi),
+            _ => Err(Error::InvalidAttribute(
+                element.name.to_string(),
+                "dpi".to_string(),
+            )),
+        }
     }
 
-    fn to_mm(px: usize) -> f64 {
-        Mm::from(Px(px).into_pt(Self::BASE_DPI)).0
+    pub fn to_mm(px: f64, dpi: f64) -> f64 {
+        Mm::from(Pt(px * 72.0 / dpi)).0
     }
 
-    fn get_scale(rect: &Rect, image: &ImageXObject) -> f64 {
-        let scale_x = rect.width / Self::to_mm(image.width.0);
-        let scale_y = rect.height / Self::to_mm(image.height.0);
-        scale_x.min(scale_y)
+    fn get_scale(object_fit: ObjectFit, rect: &Rect, width: f64, height: f64) -> (f64, f64) {
+        let scale_x = rect.width / width;
+        let scale_y = rect.height / height;
+        match object_fit {
+            ObjectFit::Contain => (scale_x.min(scale_y), scale_x.min(scale_y)),
+            ObjectFit::Cover => (scale_x.max(scale_y), scale_x.max(scale_y)),
+            ObjectFit::Fill => (scale_x, scale_y),
+            ObjectFit::None => (1.0, 1.0),
+            ObjectFit::ScaleDown => {
+                let scale = scale_x.min(scale_y).min(1.0);
+                (scale, scale)
+            }
+        }
     }
 
-    fn draw(&self, pdf_page: &PdfPageReference, rect: Rect) -> Result<(), Error> {
-        let image = self.get_image()?;
-        let scale = Self::get_scale(&rect, &image.image);
-        let rect = rect.set_height(Self::to_mm(image.image.height.0) * scale);
-        let rect = self.layout_tree.build_bottom_left_base_rect(&rect)?;
+    // Same as `object-position` of CSS, percentages align the image
+    // and lengths are offsets from the top-left of the rect.
+    fn get_offset(position: Dimension, free_space: f64) -> f64 {
+        match position {
+            Dimension::Points(offset) => offset.into(),
+            Dimension::Percent(percent) => free_space * f64::from(percent),
+            _ => 0.0,
+        }
+    }
 
-        let transform = ImageTransform {
-            translate_x: Some(Mm(rect.x)),
-            translate_y: Some(Mm(rect.y)),
-            rotate: None,
-            scale_x: Some(scale),
-            scale_y: Some(scale),
-            dpi: Some(Self::BASE_DPI),
-        };
+    fn build_clip(&self, rect: &Rect, image_rect: &Rect, clip: &Clip) -> Result<Clip, Error> {
+        let style = self.node.style;
+        let is_overflowed = image_rect.x < rect.x
+            || image_rect.y < rect.y
+            || rect.right() < image_rect.right()
+            || rect.bottom() < image_rect.bottom();
+
+        if style.overflow != Overflow::Hidden && !is_overflowed {
+            return Ok(clip.clone());
+        }
+        let rect = self.layout_tree.build_bottom_left_base_rect(rect)?;
+        Ok(clip.push(&rect, style.border_radius))
+    }
+
+    fn draw(&self, pdf_page: &PdfPageReference, rect: Rect, clip: &Clip) -> Result<(), Error> {
+        let key: String = get_required_attr(self.node.element, "src")?;
+        let images = &self.assets.images;
+        let (px_width, px_height) = images
+            .get_size(&key)
+            .ok_or_else(|| Error::ImageAssetNotFound(key.to_string()))?;
+        // Images without area can't be scaled.
+        if px_width <= 0.0 || px_height <= 0.0 {
+            return Ok(());
+        }
+        let style = self.node.style;
+        let dpi = Self::get_dpi(self.node.element, images.is_svg(&key))?;
+        let intrinsic_width = Self::to_mm(px_width, dpi);
+        let intrinsic_height = Self::to_mm(px_height, dpi);
+        let (scale_x, scale_y) =
+            Self::get_scale(style.object_fit, &rect, intrinsic_width, intrinsic_height);
+        let width = intrinsic_width * scale_x;
+        let height = intrinsic_height * scale_y;
+
+        let image_rect = Rect::new(
+            rect.x + Self::get_offset(style.object_position.x, rect.width - width),
+            rect.y + Self::get_offset(style.object_position.y, rect.height - height),
+            width,
+            height,
+        );
+        let clip = self.build_clip(&rect, &image_rect, clip)?;
+        let image_rect = self.layout_tree.build_bottom_left_base_rect(&image_rect)?;
+        let pdf_layer = clip.add_layer(pdf_page);
+
+        if let Some(svg) = images.get_svg(&key) {
+            svg.draw(&pdf_layer, &image_rect);
+            return Ok(());
+        }
 
-        let pdf_layer = pdf_page.add_layer("");
-        image.add_to_layer(pdf_layer, transform);
+        let name = images
+            .use_image(&key)
+            .ok_or_else(|| Error::ImageAssetNotFound(key.to_string()))?;
+        // Images are drawn in the unit square, so it's scaled to the rect.
+        pdf_layer.save_graphics_state();
+        pdf_layer.set_ctm(CurTransMat::Raw([
+            Pt::from(Mm(image_rect.width)).0,
+            0.0,
+            0.0,
+            Pt::from(Mm(image_rect.height)).0,
+            Pt::from(Mm(image_rect.x)).0,
+            Pt::from(Mm(image_rect.y)).0,
+        ]));
+        pdf_layer.add_operation(Operation::new("Do", vec![Object::Name(name.into_bytes())]));
+        pdf_layer.restore_graphics_state();
         Ok(())
     }
 }
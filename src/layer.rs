@@ -1,23 +1,111 @@
 use printpdf::PdfPageReference;
+use taffy::style::Dimension;
 
 use crate::{
     assets::Assets,
+    barcode::Barcode,
     border::Border,
+    chart::Chart,
+    clip::Clip,
     dom::validate_name,
     error::Error,
     hex_color::HexColor,
     image::Image,
     layout::{LayoutNode, LayoutTree},
+    list::{List, ListCounters},
+    qr_code::QrCode,
     rect::Rect,
+    shape::Shape,
+    style::Overflow,
     text::Text,
 };
 
+// Children of layers and items of lists are built by their names.
+pub fn add_child(
+    node: &LayoutNode,
+    layout_tree: &LayoutTree,
+    assets: &Assets,
+    pdf_page: &PdfPageReference,
+    rect: &Rect,
+    clip: &Clip,
+    lists: &ListCounters,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match node.element.name.as_str() {
+        "Layer" => {
+            let layer = Layer::new(node, layout_tree, assets)?;
+            layer.build(pdf_page, Some(rect), clip, lists)?;
+            Ok(())
+        }
+        "Image" => {
+            let image = Image::new(node, layout_tree, assets)?;
+            image.build(pdf_page, Some(rect), clip)?;
+            Ok(())
+        }
+        "Text" => {
+            let text = Text::new(node, layout_tree, assets)?;
+            text.build(pdf_page, Some(rect), clip)?;
+            Ok(())
+        }
+        "QrCode" => {
+            let qr_code = QrCode::new(node, layout_tree)?;
+            qr_code.build(pdf_page, Some(rect), clip)?;
+            Ok(())
+        }
+        "Chart" => {
+            let chart = Chart::new(node, layout_tree, assets)?;
+            chart.build(pdf_page, Some(rect), clip)?;
+            Ok(())
+        }
+        "Barcode" => {
+            let barcode = Barcode::new(node, layout_tree, assets)?;
+            barcode.build(pdf_page, Some(rect), clip)?;
+            Ok(())
+        }
+        "List" => {
+            let list = List::new(node, layout_tree, assets)?;
+            list.build(pdf_page, Some(rect), clip, lists)?;
+            Ok(())
+        }
+        name if Shape::NAMES.contains(&name) => {
+            let shape = Shape::new(node, layout_tree)?;
+            shape.build(pdf_page, Some(rect), clip)?;
+            Ok(())
+        }
+        _ => Err(Box::new(Error::UnknownChild(node.element.name.to_string()))),
+    }
+}
+
 pub struct Layer<'a> {
     node: &'a LayoutNode<'a>,
     layout_tree: &'a LayoutTree<'a>,
     assets: &'a Assets<'a>,
 }
 
+fn to_border_width(dimension: Dimension) -> f64 {
+    if let Dimension::Points(width) = dimension {
+        width.into()
+    } else {
+        0.0
+    }
+}
+
+// Returns the padding box inside the border and its radius,
+// which is reduced by the widest border.
+fn to_padding_box(
+    rect: &Rect,
+    border: &taffy::prelude::Rect<Dimension>,
+    border_radius: f64,
+) -> (Rect, f64) {
+    let (left, top, right, bottom) = (
+        to_border_width(border.left),
+        to_border_width(border.top),
+        to_border_width(border.right),
+        to_border_width(border.bottom),
+    );
+    let radius = border_radius - left.max(top).max(right).max(bottom);
+    (rect.inset(left, top, right, bottom), radius.max(0.0))
+}
+
 impl<'a> Layer<'a> {
     pub fn new(
         node: &'a LayoutNode,
@@ -36,57 +124,54 @@ impl<'a> Layer<'a> {
         &self,
         pdf_page: &PdfPageReference,
         parent_rect: Option<&Rect>,
+        clip: &Clip,
+        lists: &ListCounters,
     ) -> Result<(), Box<dyn std::error::Error>> {
         let rect = self
             .layout_tree
             .build_absolute_rect(self.node, parent_rect)?;
-        self.draw(pdf_page, &rect)?;
+        self.draw(pdf_page, &rect, clip)?;
 
+        let clip = self.build_child_clip(&rect, clip)?;
         for node in &self.node.children {
-            self.add_child(node, pdf_page, &rect)?;
+            add_child(
+                node,
+                self.layout_tree,
+                self.assets,
+                pdf_page,
+                &rect,
+                &clip,
+                lists,
+            )?;
         }
         Ok(())
     }
 
-    fn add_child(
-        &self,
-        node: &LayoutNode,
-        pdf_page: &PdfPageReference,
-        rect: &Rect,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        match node.element.name.as_str() {
-            "Layer" => {
-                let layer = Layer::new(node, self.layout_tree, self.assets)?;
-                layer.build(pdf_page, Some(rect))?;
-                Ok(())
-            }
-            "Image" => {
-                let image = Image::new(node, self.layout_tree, self.assets)?;
-                image.build(pdf_page, Some(rect))?;
-                Ok(())
-            }
-            "Text" => {
-                let text = Text::new(node, self.layout_tree, self.assets)?;
-                text.build(pdf_page, Some(rect))?;
-                Ok(())
-            }
-            _ => Err(Box::new(Error::UnknownChild(node.element.name.to_string()))),
+    // Descendants are clipped to the padding box same as CSS.
+    fn build_child_clip(&self, rect: &Rect, clip: &Clip) -> Result<Clip, Error> {
+        let style = self.node.style;
+        if style.overflow != Overflow::Hidden {
+            return Ok(clip.clone());
         }
+        let (padding_box, radius) = to_padding_box(rect, &style.border, style.border_radius);
+        let padding_box = self.layout_tree.build_bottom_left_base_rect(&padding_box)?;
+        Ok(clip.push(&padding_box, radius))
     }
 
     fn draw(
         &self,
         pdf_page: &PdfPageReference,
         rect: &Rect,
+        clip: &Clip,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let pdf_layer = pdf_page.add_layer("");
+        let pdf_layer = clip.add_layer(pdf_page);
         let style = self.node.style;
         let rect = self.layout_tree.build_bottom_left_base_rect(rect)?;
 
         if let Some(background_color) = &style.background_color {
             pdf_layer.set_outline_color(HexColor::new(background_color).into());
             pdf_layer.set_fill_color(HexColor::new(background_color).into());
-            pdf_layer.add_shape(rect.to_line());
+            pdf_layer.add_shape(rect.to_rounded_line(style.border_radius));
         }
 
         if let Some(border_color) = &style.border_color {
@@ -95,9 +180,71 @@ impl<'a> Layer<'a> {
                 border_color: &HexColor::new(border_color).into(),
                 rect: &rect,
                 pdf_page,
+                clip,
             }
             .draw();
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use taffy::prelude::Dimension::{Points, Undefined};
+
+    use super::*;
+
+    fn build_border(
+        left: f32,
+        top: f32,
+        right: f32,
+        bottom: f32,
+    ) -> taffy::prelude::Rect<Dimension> {
+        taffy::prelude::Rect {
+            left: Points(left),
+            top: Points(top),
+            right: Points(right),
+            bottom: Points(bottom),
+        }
+    }
+
+    #[test]
+    fn it_clips_children_to_the_padding_box() {
+        let rect = Rect::new(10.0, 20.0, 100.0, 50.0);
+        let (padding_box, _) = to_padding_box(&rect, &build_border(1.0, 2.0, 3.0, 4.0), 0.0);
+        assert_eq!(
+            (
+                padding_box.x,
+                padding_box.y,
+                padding_box.width,
+                padding_box.height
+            ),
+            (11.0, 22.0, 96.0, 44.0)
+        );
+    }
+
+    #[test]
+    fn it_reduces_the_radius_by_the_widest_border() {
+        let rect = Rect::new(0.0, 0.0, 100.0, 50.0);
+        let (_, radius) = to_padding_box(&rect, &build_border(1.0, 3.0, 2.0, 1.0), 8.0);
+        assert_eq!(radius, 5.0);
+        let (_, radius) = to_padding_box(&rect, &build_border(10.0, 0.0, 0.0, 0.0), 8.0);
+        assert_eq!(radius, 0.0);
+    }
+
+    #[test]
+    fn it_ignores_undefined_borders() {
+        let border = taffy::prelude::Rect {
+            left: Undefined,
+            top: Undefined,
+            right: Undefined,
+            bottom: Undefined,
+        };
+        let rect = Rect::new(0.0, 0.0, 100.0, 50.0);
+        let (padding_box, radius) = to_padding_box(&rect, &border, 8.0);
+        assert_eq!(
+            (padding_box.width, padding_box.height, radius),
+            (100.0, 50.0, 8.0)
+        );
+    }
+}
@@ -2,12 +2,21 @@ use std::vec;
 
 use taffy::{
     error::TaffyError,
-    prelude::{Node, Size},
-    style::Dimension,
+    node::MeasureFunc,
+    prelude::{AvailableSpace, Node, Size},
+    style::{Dimension, FlexDirection},
     Taffy,
 };
 
-use crate::{assets::StyleAsset, dom, error::Error, rect::Rect, style::Style};
+use crate::{
+    assets::{ImageAsset, StyleAsset},
+    dom,
+    error::Error,
+    image::Image,
+    list::List,
+    rect::Rect,
+    style::Style,
+};
 
 pub struct LayoutNode<'a> {
     pub element: &'a dom::Element,
@@ -56,7 +65,7 @@ impl<'a> LayoutTree<'a> {
         let rect = self.build_rect(node.taffy_node)?;
 
         if let Some(parent_rect) = parent_rect {
-            Ok(rect.add_position(&parent_rect))
+            Ok(rect.add_position(parent_rect))
         } else {
             Ok(rect)
         }
@@ -83,11 +92,79 @@ impl<'a> LayoutTree<'a> {
 #[derive(Debug)]
 pub struct LayoutTreeBuilder<'a> {
     pub styles: &'a StyleAsset<'a>,
+    pub images: &'a ImageAsset<'a>,
 }
 
 impl<'a> LayoutTreeBuilder<'a> {
-    pub fn new(styles: &'a StyleAsset<'a>) -> LayoutTreeBuilder {
-        Self { styles }
+    pub fn new(styles: &'a StyleAsset<'a>, images: &'a ImageAsset<'a>) -> LayoutTreeBuilder<'a> {
+        Self { styles, images }
+    }
+
+    fn new_leaf(
+        &self,
+        current: &dom::Element,
+        style: &Style,
+        taffy: &mut Taffy,
+    ) -> Result<Node, TaffyError> {
+        if current.name == "Image" {
+            if let Some(intrinsic_size) = self.get_intrinsic_image_size(current) {
+                let measure = Self::build_image_measure(intrinsic_size, style.aspect_ratio);
+                return taffy.new_leaf_with_measure(style.into(), measure);
+            }
+        }
+        if current.name == "List" {
+            return taffy.new_leaf(Self::build_list_style(style));
+        }
+        taffy.new_leaf(style.into())
+    }
+
+    // Items of a list are stacked vertically, and markers are written in the left padding.
+    fn build_list_style(style: &Style) -> taffy::style::Style {
+        let mut list_style: taffy::style::Style = style.into();
+        list_style.flex_direction = FlexDirection::Column;
+        if style.padding.left == Dimension::Undefined {
+            list_style.padding.left = Dimension::Points(List::DEFAULT_GUTTER);
+        }
+        list_style
+    }
+
+    // Images without area have no intrinsic size, and an invalid dpi is reported when drawing.
+    fn get_intrinsic_image_size(&self, element: &dom::Element) -> Option<Size<f32>> {
+        let key: String = dom::get_attr(element, "src")?;
+        let (width, height) = self.images.get_size(&key)?;
+        if width <= 0.0 || height <= 0.0 {
+            return None;
+        }
+        let dpi = Image::get_dpi(element, self.images.is_svg(&key)).ok()?;
+        Some(Size {
+            width: Image::to_mm(width, dpi) as f32,
+            height: Image::to_mm(height, dpi) as f32,
+        })
+    }
+
+    // An image keeps its aspect ratio when only one side is fixed by the style,
+    // and falls back to its intrinsic size when no side is fixed.
+    fn build_image_measure(intrinsic_size: Size<f32>, aspect_ratio: Option<f32>) -> MeasureFunc {
+        let ratio = aspect_ratio
+            .filter(|ratio| *ratio > 0.0)
+            .unwrap_or(intrinsic_size.width / intrinsic_size.height);
+        MeasureFunc::Boxed(Box::new(
+            move |known: Size<Option<f32>>, _: Size<AvailableSpace>| match (
+                known.width,
+                known.height,
+            ) {
+                (Some(width), Some(height)) => Size { width, height },
+                (Some(width), None) => Size {
+                    width,
+                    height: width / ratio,
+                },
+                (None, Some(height)) => Size {
+                    width: height * ratio,
+                    height,
+                },
+                (None, None) => intrinsic_size,
+            },
+        ))
     }
 
     fn walk(
@@ -95,11 +172,18 @@ impl<'a> LayoutTreeBuilder<'a> {
         current: &'a dom::Element,
         taffy: &mut Taffy,
         parent_taffy_node: Option<Node>,
-    ) -> Result<LayoutNode, TaffyError> {
+    ) -> Result<LayoutNode<'a>, TaffyError> {
         let style = self.find_style(current);
-        let taffy_node = taffy.new_leaf(style.into())?;
-
-        if let dom::Children::Elements(children) = &current.children {
+        let taffy_node = self.new_leaf(current, style, taffy)?;
+
+        // Children of a chart are data and children of a text are inline,
+        // so they aren't laid out.
+        let children = match &current.children {
+            dom::Children::Elements(_) if current.name == "Chart" || current.name == "Text" => None,
+            dom::Children::Elements(children) => Some(children),
+            dom::Children::Text(_) | dom::Children::Inline(_) => None,
+        };
+        if let Some(children) = children {
             let children: Vec<LayoutNode> = children
                 .iter()
                 .map(|child| self.walk(child, taffy, Some(taffy_node)))
@@ -125,7 +209,7 @@ impl<'a> LayoutTreeBuilder<'a> {
         }
     }
 
-    fn find_style(&self, element: &dom::Element) -> &Style {
+    fn find_style(&self, element: &dom::Element) -> &Style<'a> {
         if let Some(key) = element.attributes.get("style") {
             self.styles.get(key).unwrap_or(&Style::DEFAULT)
         } else {
@@ -133,7 +217,7 @@ impl<'a> LayoutTreeBuilder<'a> {
         }
     }
 
-    pub fn build(&'a self, root: &'a dom::Element) -> Result<LayoutTree, TaffyError> {
+    pub fn build(&'a self, root: &'a dom::Element) -> Result<LayoutTree<'a>, TaffyError> {
         let mut taffy = Taffy::new();
         let node = self.walk(root, &mut taffy, None)?;
         taffy.compute_layout(node.taffy_node, Size::MAX_CONTENT)?;
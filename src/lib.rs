@@ -1,13 +1,26 @@
 pub mod assets;
+pub mod barcode;
 pub mod border;
+pub mod chart;
+pub mod clip;
 pub mod document;
 pub mod dom;
 pub mod error;
 pub mod hex_color;
+pub mod hyphenation;
 pub mod image;
 pub mod layer;
 pub mod layout;
+pub mod line_breaking;
+pub mod list;
+pub mod output;
 pub mod page;
+pub mod path;
+pub mod qr_code;
 pub mod rect;
+pub mod shape;
+pub mod shaping;
 pub mod style;
+pub mod subset;
+pub mod svg;
 pub mod text;
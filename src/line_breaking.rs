@@ -0,0 +1,348 @@
+// Total-fit line breaking by the algorithm of Knuth and Plass.
+// Breaks are chosen to minimize the sum of demerits of all lines of the paragraph,
+// instead of filling each line as much as possible.
+
+use unicode_linebreak::linebreaks;
+
+// Penalties of this cost or more can't be broken, and `-INFINITE_PENALTY` is a hard break.
+pub const INFINITE_PENALTY: f64 = 10000.0;
+const LINE_PENALTY: f64 = 10.0;
+// Added when two lines in a row end with flagged penalties, e.g. hyphens.
+const FLAGGED_DEMERITS: f64 = 3000.0;
+// Added when a tight line is next to a loose line.
+const FITNESS_DEMERITS: f64 = 100.0;
+// Lines can be stretched up to this ratio of their stretchability.
+const TOLERANCE: f64 = 3.0;
+const INFINITE_BADNESS: f64 = 10000.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Item {
+    // A character that can't be split.
+    Box {
+        width: f64,
+    },
+    // A space that can be stretched or shrunk. Lines can be broken at it after a box.
+    Glue {
+        width: f64,
+        stretch: f64,
+        shrink: f64,
+    },
+    // Lines can be broken at it by the cost, and the width is added to the end of the line.
+    Penalty {
+        width: f64,
+        cost: f64,
+        is_flagged: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Totals {
+    width: f64,
+    stretch: f64,
+    shrink: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    // The index of the item where the line before is broken.
+    position: usize,
+    fitness: usize,
+    is_flagged: bool,
+    // Totals of items from the start of the paragraph to the start of the next line.
+    totals: Totals,
+    demerits: f64,
+    previous: Option<usize>,
+}
+
+// Returns the ratio of how much the line is stretched (positive) or shrunk (negative).
+fn compute_ratio(width: f64, max_width: f64, stretch: f64, shrink: f64) -> f64 {
+    if width < max_width {
+        if stretch > 0.0 {
+            (max_width - width) / stretch
+        } else {
+            f64::INFINITY
+        }
+    } else if width > max_width {
+        if shrink > 0.0 {
+            (max_width - width) / shrink
+        } else {
+            f64::NEG_INFINITY
+        }
+    } else {
+        0.0
+    }
+}
+
+// Very tight, tight, loose and very loose lines same as TeX.
+fn get_fitness(ratio: f64) -> usize {
+    if ratio < -0.5 {
+        0
+    } else if ratio <= 0.5 {
+        1
+    } else if ratio <= 1.0 {
+        2
+    } else {
+        3
+    }
+}
+
+// Glues after the break are removed, so the next line starts with a box.
+fn compute_totals_after(items: &[Item], position: usize, mut totals: Totals) -> Totals {
+    for (index, item) in items.iter().enumerate().skip(position) {
+        match *item {
+            Item::Box { .. } => break,
+            Item::Glue {
+                width,
+                stretch,
+                shrink,
+            } => {
+                totals.width += width;
+                totals.stretch += stretch;
+                totals.shrink += shrink;
+            }
+            Item::Penalty { cost, .. } if cost <= -INFINITE_PENALTY && index > position => break,
+            Item::Penalty { .. } => {}
+        }
+    }
+    totals
+}
+
+// Returns indices of items where lines are broken, including the last one.
+// The paragraph should end with a hard break. Lines that can't be stretched enough
+// are allowed if there is no other way same as the emergency pass of TeX,
+// and `None` is returned if a line overflows, e.g. a word is wider than the line.
+pub fn find_optimal_breaks(items: &[Item], max_width: f64) -> Option<Vec<usize>> {
+    find_breaks(items, max_width, TOLERANCE)
+        .or_else(|| find_breaks(items, max_width, f64::INFINITY))
+}
+
+fn find_breaks(items: &[Item], max_width: f64, tolerance: f64) -> Option<Vec<usize>> {
+    let mut nodes = vec![Node {
+        position: 0,
+        fitness: 1,
+        is_flagged: false,
+        totals: Totals::default(),
+        demerits: 0.0,
+        previous: None,
+    }];
+    let mut active = vec![0];
+    let mut totals = Totals::default();
+
+    for (position, item) in items.iter().enumerate() {
+        let breakpoint = match *item {
+            Item::Glue { .. }
+                if position > 0 && matches!(items[position - 1], Item::Box { .. }) =>
+            {
+                Some((0.0, 0.0, false))
+            }
+            Item::Penalty {
+                width,
+                cost,
+                is_flagged,
+            } if cost < INFINITE_PENALTY => Some((width, cost, is_flagged)),
+            _ => None,
+        };
+
+        if let Some((penalty_width, cost, is_flagged)) = breakpoint {
+            // The best node of each fitness class.
+            let mut candidates: [Option<(f64, usize)>; 4] = [None; 4];
+            let mut index = 0;
+            while index < active.len() {
+                let node_index = active[index];
+                let node = nodes[node_index];
+                let ratio = compute_ratio(
+                    totals.width - node.totals.width + penalty_width,
+                    max_width,
+                    totals.stretch - node.totals.stretch,
+                    totals.shrink - node.totals.shrink,
+                );
+                if ratio < -1.0 || cost <= -INFINITE_PENALTY {
+                    active.remove(index);
+                } else {
+                    index += 1;
+                }
+                if !(-1.0..=tolerance).contains(&ratio) {
+                    continue;
+                }
+
+                let badness = (100.0 * ratio.abs().powi(3)).min(INFINITE_BADNESS);
+                let mut demerits = (LINE_PENALTY + badness).powi(2);
+                if cost >= 0.0 {
+                    demerits += cost.powi(2);
+                } else if cost > -INFINITE_PENALTY {
+                    demerits -= cost.powi(2);
+                }
+                if is_flagged && node.is_flagged {
+                    demerits += FLAGGED_DEMERITS;
+                }
+                let fitness = get_fitness(ratio);
+                if fitness.abs_diff(node.fitness) > 1 {
+                    demerits += FITNESS_DEMERITS;
+                }
+                demerits += node.demerits;
+                if candidates[fitness].map_or(true, |(best, _)| demerits < best) {
+                    candidates[fitness] = Some((demerits, node_index));
+                }
+            }
+
+            for (fitness, candidate) in candidates.iter().enumerate() {
+                if let Some((demerits, previous)) = *candidate {
+                    nodes.push(Node {
+                        position,
+                        fitness,
+                        is_flagged,
+                        totals: compute_totals_after(items, position, totals),
+                        demerits,
+                        previous: Some(previous),
+                    });
+                    active.push(nodes.len() - 1);
+                }
+            }
+            if active.is_empty() {
+                return None;
+            }
+        }
+
+        match *item {
+            Item::Box { width } => totals.width += width,
+            Item::Glue {
+                width,
+                stretch,
+                shrink,
+            } => {
+                totals.width += width;
+                totals.stretch += stretch;
+                totals.shrink += shrink;
+            }
+            Item::Penalty { .. } => {}
+        }
+    }
+
+    let mut current = active
+        .iter()
+        .copied()
+        .filter(|index| nodes[*index].previous.is_some())
+        .min_by(|a, b| nodes[*a].demerits.total_cmp(&nodes[*b].demerits));
+    let mut breaks = Vec::new();
+    while let Some(index) = current.filter(|index| nodes[*index].previous.is_some()) {
+        breaks.push(nodes[index].position);
+        current = nodes[index].previous;
+    }
+    breaks.reverse();
+    Some(breaks)
+}
+
+// Returns positions of bytes in ascending order where lines can start by the Unicode line
+// breaking algorithm (UAX #14), e.g. after spaces and between CJK characters.
+// Breaks after soft hyphens are left to hyphenation.
+pub fn find_break_opportunities(text: &str) -> Vec<usize> {
+    linebreaks(text)
+        .map(|(position, _)| position)
+        .filter(|position| !text[..*position].ends_with('\u{AD}'))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GLUE: Item = Item::Glue {
+        width: 1.0,
+        stretch: 1.0,
+        shrink: 0.5,
+    };
+
+    // Words are boxes separated by glues, and the paragraph ends same as `MultiLine`.
+    fn build_items(words: &[f64]) -> Vec<Item> {
+        let mut items = Vec::new();
+        for (index, width) in words.iter().enumerate() {
+            if index > 0 {
+                items.push(GLUE);
+            }
+            items.push(Item::Box { width: *width });
+        }
+        items.extend([
+            Item::Penalty {
+                width: 0.0,
+                cost: INFINITE_PENALTY,
+                is_flagged: false,
+            },
+            Item::Glue {
+                width: 0.0,
+                stretch: 1000.0,
+                shrink: 0.0,
+            },
+            Item::Penalty {
+                width: 0.0,
+                cost: -INFINITE_PENALTY,
+                is_flagged: false,
+            },
+        ]);
+        items
+    }
+
+    #[test]
+    fn it_can_break_lines() {
+        let items = build_items(&[3.0, 3.0]);
+        assert_eq!(find_optimal_breaks(&items, 10.0), Some(vec![5]));
+        assert_eq!(find_optimal_breaks(&items, 5.0), Some(vec![1, 5]));
+    }
+
+    #[test]
+    fn it_minimizes_demerits_of_the_paragraph() {
+        // Greedy breaking stretches "3 1" very much,
+        // but "3 1 3" is shrunk a little with the last line "3".
+        let items = build_items(&[3.0, 1.0, 3.0, 3.0]);
+        assert_eq!(find_optimal_breaks(&items, 8.0), Some(vec![5, 9]));
+    }
+
+    #[test]
+    fn it_can_break_at_penalties() {
+        let mut items = build_items(&[3.0, 1.5, 3.0]);
+        // The second word is split into two boxes by a hyphen.
+        items.splice(
+            2..3,
+            [
+                Item::Box { width: 1.5 },
+                Item::Penalty {
+                    width: 0.5,
+                    cost: 50.0,
+                    is_flagged: true,
+                },
+                Item::Box { width: 1.5 },
+            ],
+        );
+        assert_eq!(find_optimal_breaks(&items, 7.0), Some(vec![5, 9]));
+        assert_eq!(find_optimal_breaks(&items, 6.5), Some(vec![3, 9]));
+    }
+
+    #[test]
+    fn it_cannot_break_when_a_box_is_too_wide() {
+        let items = build_items(&[3.0, 12.0]);
+        assert_eq!(find_optimal_breaks(&items, 10.0), None);
+    }
+
+    #[test]
+    fn it_finds_break_opportunities_after_spaces() {
+        assert_eq!(find_break_opportunities("ab cd"), vec![3, 5]);
+        assert_eq!(find_break_opportunities("ab\u{A0}cd"), vec![6]);
+    }
+
+    #[test]
+    fn it_finds_break_opportunities_between_cjk_characters() {
+        assert_eq!(find_break_opportunities("日本語"), vec![3, 6, 9]);
+        // Small kana and closing punctuations can't start lines.
+        assert_eq!(find_break_opportunities("ちょっと。"), vec![9, 15]);
+    }
+
+    #[test]
+    fn it_finds_break_opportunities_in_mixed_texts() {
+        assert_eq!(find_break_opportunities("PDFを作る"), vec![3, 6, 9, 12]);
+        assert_eq!(find_break_opportunities("日本 PDF"), vec![3, 7, 10]);
+    }
+
+    #[test]
+    fn it_leaves_soft_hyphens_to_hyphenation() {
+        assert_eq!(find_break_opportunities("hy\u{AD}phen"), vec![8]);
+    }
+}
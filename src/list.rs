@@ -0,0 +1,347 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    str::FromStr,
+};
+
+use printpdf::{Mm, PdfPageReference};
+
+use crate::{
+    assets::Assets,
+    clip::Clip,
+    dom::{get_attr, validate_name},
+    error::Error,
+    layer::add_child,
+    layout::{LayoutNode, LayoutTree},
+    path::get_fill_color,
+    rect::Rect,
+    text::{get_text_width, to_mm_size, use_text},
+};
+
+// Bullets of nested lists change by the depth same as browsers.
+const BULLETS: [&str; 3] = ["\u{2022}", "\u{25e6}", "\u{25aa}"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListType {
+    Bullet,
+    Decimal,
+    LowerAlpha,
+    Roman,
+}
+
+impl FromStr for ListType {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "bullet" => Ok(Self::Bullet),
+            "decimal" => Ok(Self::Decimal),
+            "lower-alpha" => Ok(Self::LowerAlpha),
+            "roman" => Ok(Self::Roman),
+            _ => Err(format!("Unknown list type: {}", input)),
+        }
+    }
+}
+
+impl ListType {
+    pub fn to_marker(self, number: usize, depth: usize) -> String {
+        match self {
+            Self::Bullet => BULLETS[depth % BULLETS.len()].to_string(),
+            Self::Decimal => format!("{}.", number),
+            Self::LowerAlpha => format!("{}.", to_alpha(number)),
+            Self::Roman => format!("{}.", to_roman(number)),
+        }
+    }
+}
+
+// a, b, ..., z, aa, ab, ... same as `lower-alpha` of CSS.
+fn to_alpha(mut number: usize) -> String {
+    let mut letters = Vec::new();
+    while number > 0 {
+        number -= 1;
+        letters.push((b'a' + (number % 26) as u8) as char);
+        number /= 26;
+    }
+    letters.iter().rev().collect()
+}
+
+// Lowercase roman numerals, numbers out of 1 to 3999 are written in decimal.
+fn to_roman(mut number: usize) -> String {
+    const NUMERALS: [(usize, &str); 13] = [
+        (1000, "m"),
+        (900, "cm"),
+        (500, "d"),
+        (400, "cd"),
+        (100, "c"),
+        (90, "xc"),
+        (50, "l"),
+        (40, "xl"),
+        (10, "x"),
+        (9, "ix"),
+        (5, "v"),
+        (4, "iv"),
+        (1, "i"),
+    ];
+    if !(1..4000).contains(&number) {
+        return number.to_string();
+    }
+    let mut roman = String::new();
+    for (value, numeral) in NUMERALS {
+        while number >= value {
+            roman.push_str(numeral);
+            number -= value;
+        }
+    }
+    roman
+}
+
+// Lists are counted while building the document, so a list with the `continue` attribute
+// continues numbering from the previous list of the same depth even on another page.
+// It's created for each build and passed to lists through children of layers.
+#[derive(Debug, Default)]
+pub struct ListCounters {
+    depth: Cell<usize>,
+    last_numbers: RefCell<HashMap<usize, usize>>,
+}
+
+pub struct List<'a> {
+    node: &'a LayoutNode<'a>,
+    layout_tree: &'a LayoutTree<'a>,
+    assets: &'a Assets<'a>,
+}
+
+impl<'a> List<'a> {
+    // The width of the gutter for markers when the left padding isn't given.
+    pub const DEFAULT_GUTTER: f32 = 8.0;
+
+    pub fn new(
+        node: &'a LayoutNode,
+        layout_tree: &'a LayoutTree,
+        assets: &'a Assets<'a>,
+    ) -> Result<Self, Error> {
+        validate_name(node.element, "List")?;
+        Ok(Self {
+            node,
+            layout_tree,
+            assets,
+        })
+    }
+
+    pub fn build(
+        &self,
+        pdf_page: &PdfPageReference,
+        parent_rect: Option<&Rect>,
+        clip: &Clip,
+        counters: &ListCounters,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let rect = self
+            .layout_tree
+            .build_absolute_rect(self.node, parent_rect)?;
+        let element = self.node.element;
+        let list_type = get_attr(element, "type").unwrap_or(ListType::Bullet);
+        let depth = counters.depth.get();
+
+        let start = if get_attr(element, "continue").unwrap_or(false) {
+            counters
+                .last_numbers
+                .borrow()
+                .get(&depth)
+                .map_or(1, |last| last + 1)
+        } else {
+            get_attr(element, "start").unwrap_or(1)
+        };
+        // The depth is restored even if an item fails to be built.
+        counters.depth.set(depth + 1);
+        let result = (start..).zip(&self.node.children).try_for_each(
+            |(number, node)| -> Result<(), Box<dyn std::error::Error>> {
+                validate_name(node.element, "Item")?;
+                let item_rect = self.layout_tree.build_absolute_rect(node, Some(&rect))?;
+                self.draw_marker(
+                    &list_type.to_marker(number, depth),
+                    pdf_page,
+                    &item_rect,
+                    clip,
+                )?;
+                counters.last_numbers.borrow_mut().insert(depth, number);
+                for child in &node.children {
+                    add_child(
+                        child,
+                        self.layout_tree,
+                        self.assets,
+                        pdf_page,
+                        &item_rect,
+                        clip,
+                        counters,
+                    )?;
+                }
+                Ok(())
+            },
+        );
+        counters.depth.set(depth);
+        result
+    }
+
+    // The marker is right-aligned in the gutter, and its baseline is same as
+    // the first line of a text at the top of the item in the same font size.
+    fn draw_marker(
+        &self,
+        marker: &str,
+        pdf_page: &PdfPageReference,
+        item_rect: &Rect,
+        clip: &Clip,
+    ) -> Result<(), Error> {
+        let style = self.node.style;
+        let fonts =
+            self.assets
+                .fonts
+                .get_fallbacks(style.font_family, style.font_weight, style.font_style);
+        let c = marker.chars().next().unwrap_or(' ');
+        let font = fonts
+            .iter()
+            .find(|font| font.has_glyph(c))
+            .or_else(|| fonts.first())
+            .ok_or_else(|| Error::FontAssetNotFound(style.font_family.to_string()))?;
+
+        let em = to_mm_size(style.font_size);
+        let width = get_text_width(font, marker, style.font_size, &[]);
+        let rect = self
+            .layout_tree
+            .build_bottom_left_base_rect(&item_rect.set_height(em))?;
+        let pdf_layer = clip.add_layer(pdf_page);
+        pdf_layer.set_fill_color(get_fill_color(style.fill_color));
+        use_text(
+            &pdf_layer,
+            font,
+            marker,
+            style.font_size,
+            &[],
+            (Mm(rect.x - em / 2.0 - width), Mm(rect.y)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lopdf::{content::Content, Object};
+    use taffy::prelude::{Dimension::Points, Size};
+
+    use super::*;
+    use crate::{document::Document, dom, style::Style, subset::build_font};
+
+    // A font that has only `.` and digits, and the glyph id of `c` is `c - 0x2D`.
+    fn build_digit_font() -> Vec<u8> {
+        let num_glyphs = 13u16;
+        let mut head = vec![0; 54];
+        head[0..4].copy_from_slice(&0x0001_0000u32.to_be_bytes());
+        head[18..20].copy_from_slice(&1000u16.to_be_bytes());
+        let mut hhea = vec![0; 36];
+        hhea[0..4].copy_from_slice(&0x0001_0000u32.to_be_bytes());
+        hhea[4..6].copy_from_slice(&800i16.to_be_bytes());
+        hhea[6..8].copy_from_slice(&(-200i16).to_be_bytes());
+        hhea[34..36].copy_from_slice(&num_glyphs.to_be_bytes());
+        let mut maxp = 0x0000_5000u32.to_be_bytes().to_vec();
+        maxp.extend_from_slice(&num_glyphs.to_be_bytes());
+        let hmtx = (0..num_glyphs).flat_map(|_| [1, 244, 0, 0]).collect();
+
+        // A format 4 subtable with a segment from `.` to `9` and the last one.
+        let subtable = [
+            4, 32, 0, 4, 4, 1, 0, 0x39, 0xFFFF, 0, 0x2E, 0xFFFF, 0xFFD3, 1, 0, 0,
+        ]
+        .iter()
+        .flat_map(|value: &u16| value.to_be_bytes());
+        let mut cmap = vec![0, 0, 0, 1, 0, 3, 0, 1, 0, 0, 0, 12];
+        cmap.extend(subtable);
+
+        build_font(&[
+            (b"cmap", cmap),
+            (b"head", head),
+            (b"hhea", hhea),
+            (b"hmtx", hmtx),
+            (b"maxp", maxp),
+        ])
+    }
+
+    // Returns texts written in each page by glyph ids of the digit font.
+    fn get_page_texts(doc: &lopdf::Document) -> Vec<String> {
+        doc.get_pages()
+            .into_values()
+            .map(|page_id| {
+                let content = Content::decode(&doc.get_page_content(page_id).unwrap()).unwrap();
+                content
+                    .operations
+                    .iter()
+                    .filter(|operation| operation.operator == "TJ")
+                    .flat_map(|operation| operation.operands[0].as_array().unwrap())
+                    .filter_map(|operand| match operand {
+                        Object::String(bytes, _) => Some(bytes.clone()),
+                        _ => None,
+                    })
+                    .flat_map(|bytes| {
+                        bytes
+                            .chunks(2)
+                            .map(|id| char::from(id[1] + 0x2D))
+                            .collect::<Vec<_>>()
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn it_can_parse_list_type() {
+        assert_eq!("lower-alpha".parse(), Ok(ListType::LowerAlpha));
+        assert!("upper-alpha".parse::<ListType>().is_err());
+    }
+
+    #[test]
+    fn it_changes_bullets_by_depth() {
+        assert_eq!(ListType::Bullet.to_marker(3, 0), "\u{2022}");
+        assert_eq!(ListType::Bullet.to_marker(3, 1), "\u{25e6}");
+        assert_eq!(ListType::Bullet.to_marker(3, 3), "\u{2022}");
+    }
+
+    #[test]
+    fn it_can_write_numbers_in_letters() {
+        assert_eq!(ListType::Decimal.to_marker(12, 0), "12.");
+        assert_eq!(to_alpha(1), "a");
+        assert_eq!(to_alpha(26), "z");
+        assert_eq!(to_alpha(28), "ab");
+    }
+
+    #[test]
+    fn it_can_write_numbers_in_roman_numerals() {
+        assert_eq!(to_roman(4), "iv");
+        assert_eq!(to_roman(1994), "mcmxciv");
+        assert_eq!(to_roman(0), "0");
+    }
+
+    #[test]
+    fn it_continues_numbering_from_the_list_on_the_previous_page() {
+        let font = build_digit_font();
+        let mut assets = Assets::default();
+        assets.fonts.add("default", &font);
+        assets.styles.add(
+            "page",
+            Style {
+                size: Size {
+                    width: Points(100.0),
+                    height: Points(100.0),
+                },
+                ..Style::DEFAULT
+            },
+        );
+        let list = r#"<Layer><List type="decimal" {}><Item /><Item /></List></Layer>"#;
+        let xml = format!(
+            r#"<Document><Page style="page">{}</Page><Page style="page">{}</Page></Document>"#,
+            list.replace("{}", ""),
+            list.replace("{}", r#"continue="true""#),
+        );
+        let root = dom::parse(&xml).unwrap();
+        let mut document = Document::new(&root, assets).unwrap();
+        for _ in 0..2 {
+            let output = document.build().unwrap();
+            let doc = lopdf::Document::load_mem(&output.save_to_bytes().unwrap()).unwrap();
+            assert_eq!(get_page_texts(&doc), vec!["1.2.", "3.4."]);
+        }
+    }
+}
@@ -0,0 +1,505 @@
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    io::{BufWriter, Write},
+};
+
+use lopdf::{dictionary, Dictionary, Object, ObjectId, Stream};
+use printpdf::PdfDocumentReference;
+
+use crate::assets::ImageAssetItem;
+
+// A font written in the document, which is embedded as a Type0 font with the glyphs used in it.
+// Glyphs such as ligatures need widths and texts too because they have no characters.
+#[derive(Debug, Clone)]
+pub struct EmbeddedFont {
+    // The name of the font resource in pages.
+    pub name: String,
+    // The PostScript name, which is tagged if the font file is a subset.
+    pub base_font: String,
+    // The subset, or the whole font if it can't be subset.
+    pub font_file: Vec<u8>,
+    // Metrics in 1/1000 em.
+    pub ascent: f64,
+    pub descent: f64,
+    pub bbox: [f64; 4],
+    // Widths in 1/1000 em and texts of glyphs used in the document.
+    pub glyphs: BTreeMap<u16, (f64, String)>,
+}
+
+impl EmbeddedFont {
+    // Adds the font to the document and returns the id of the Type0 font.
+    // The font file is embedded without compression, and `Length1` is the length of it.
+    // Texts are written as glyph ids, so CIDs are mapped to glyph ids by Identity.
+    pub fn embed(&self, doc: &mut lopdf::Document) -> ObjectId {
+        let mut font_file = Stream::new(
+            dictionary! { "Length1" => self.font_file.len() as i64 },
+            self.font_file.clone(),
+        );
+        font_file.allows_compression = false;
+        let descriptor = dictionary! {
+            "Type" => "FontDescriptor",
+            "FontName" => Object::Name(self.base_font.clone().into_bytes()),
+            "Flags" => 32,
+            "FontBBox" => self.bbox.iter().map(|value| Object::Real(*value)).collect::<Vec<_>>(),
+            "ItalicAngle" => 0,
+            "Ascent" => self.ascent,
+            "Descent" => self.descent,
+            "CapHeight" => self.ascent,
+            "StemV" => 80,
+            "FontFile2" => doc.add_object(font_file),
+        };
+        let cid_font = dictionary! {
+            "Type" => "Font",
+            "Subtype" => "CIDFontType2",
+            "BaseFont" => Object::Name(self.base_font.clone().into_bytes()),
+            "CIDSystemInfo" => dictionary! {
+                "Registry" => Object::string_literal("Adobe"),
+                "Ordering" => Object::string_literal("Identity"),
+                "Supplement" => 0,
+            },
+            "FontDescriptor" => doc.add_object(descriptor),
+            "CIDToGIDMap" => "Identity",
+            "DW" => 1000,
+            "W" => build_widths(&self.glyphs),
+        };
+        let type0_font = dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type0",
+            "BaseFont" => Object::Name(self.base_font.clone().into_bytes()),
+            "Encoding" => "Identity-H",
+            "DescendantFonts" => vec![Object::Dictionary(cid_font)],
+            "ToUnicode" => doc.add_object(build_to_unicode(&self.glyphs)),
+        };
+        doc.add_object(type0_font)
+    }
+}
+
+// An image drawn in the document, which is embedded once and shared by all pages.
+#[derive(Debug, Clone)]
+pub struct EmbeddedImage {
+    // The name of the XObject resource in pages.
+    pub name: String,
+    pub image: ImageAssetItem,
+}
+
+fn build_widths(glyphs: &BTreeMap<u16, (f64, String)>) -> Object {
+    Object::Array(
+        glyphs
+            .iter()
+            .flat_map(|(id, (width, _))| {
+                [
+                    Object::Integer(i64::from(*id)),
+                    Object::Array(vec![(*width).into()]),
+                ]
+            })
+            .collect(),
+    )
+}
+
+// Maps glyph ids to texts in UTF-16 to copy texts from the PDF.
+fn build_to_unicode(glyphs: &BTreeMap<u16, (f64, String)>) -> Stream {
+    let chars: Vec<(u16, &str)> = glyphs
+        .iter()
+        .filter(|(_, (_, text))| !text.is_empty())
+        .map(|(id, (_, text))| (*id, text.as_str()))
+        .collect();
+    let mut cmap = String::from(concat!(
+        "/CIDInit /ProcSet findresource begin\n",
+        "12 dict begin\n",
+        "begincmap\n",
+        "/CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n",
+        "/CMapName /Adobe-Identity-UCS def\n",
+        "/CMapType 2 def\n",
+        "1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n",
+    ));
+    // A block can have up to 100 entries.
+    for block in chars.chunks(100) {
+        cmap.push_str(&format!("{} beginbfchar\n", block.len()));
+        for (id, text) in block {
+            let units: String = text
+                .encode_utf16()
+                .map(|unit| format!("{:04X}", unit))
+                .collect();
+            cmap.push_str(&format!("<{:04X}> <{}>\n", id, units));
+        }
+        cmap.push_str("endbfchar\n");
+    }
+    cmap.push_str("endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend\n");
+    Stream::new(Dictionary::new(), cmap.into_bytes())
+}
+
+// Adds the resources of the kind to all pages with the ones printpdf has added.
+// Printpdf refers to the resources of each page, and the kind is written directly in it
+// or refers to a dictionary shared by pages.
+fn add_resources(doc: &mut lopdf::Document, kind: &[u8], resources: Dictionary) {
+    if resources.is_empty() {
+        return;
+    }
+    let shared_id = doc.add_object(resources.clone());
+    for page_id in doc.get_pages().into_values() {
+        let resources_id = match doc
+            .get_dictionary(page_id)
+            .and_then(|page| page.get(b"Resources"))
+        {
+            Ok(Object::Reference(id)) => *id,
+            _ => {
+                let id = doc.add_object(Dictionary::new());
+                if let Ok(page) = doc.get_object_mut(page_id).and_then(Object::as_dict_mut) {
+                    page.set("Resources", id);
+                }
+                id
+            }
+        };
+        let page_resources = match doc
+            .get_object_mut(resources_id)
+            .and_then(Object::as_dict_mut)
+        {
+            Ok(page_resources) => page_resources,
+            Err(_) => continue,
+        };
+        let existing_id = match page_resources.get_mut(kind) {
+            Ok(Object::Dictionary(existing)) => {
+                existing.extend(&resources);
+                continue;
+            }
+            Ok(Object::Reference(id)) if *id != shared_id => *id,
+            _ => {
+                page_resources.set(kind, shared_id);
+                continue;
+            }
+        };
+        if let Ok(existing) = doc
+            .get_object_mut(existing_id)
+            .and_then(Object::as_dict_mut)
+        {
+            existing.extend(&resources);
+        }
+    }
+}
+
+// Embeds images and fonts into the document built by printpdf when saving,
+// and pages refer to them by the names given by assets.
+pub struct Output {
+    pdf_doc: PdfDocumentReference,
+    images: Vec<EmbeddedImage>,
+    fonts: Vec<EmbeddedFont>,
+}
+
+impl Output {
+    pub fn new(
+        pdf_doc: PdfDocumentReference,
+        images: Vec<EmbeddedImage>,
+        fonts: Vec<EmbeddedFont>,
+    ) -> Self {
+        Self {
+            pdf_doc,
+            images,
+            fonts,
+        }
+    }
+
+    pub fn save<W: Write>(self, target: &mut BufWriter<W>) -> Result<(), Box<dyn Error>> {
+        target.write_all(&self.save_to_bytes()?)?;
+        Ok(())
+    }
+
+    pub fn save_to_bytes(self) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut doc = lopdf::Document::load_mem(&self.pdf_doc.save_to_bytes()?)?;
+        let xobjects = Self::embed_images(&mut doc, &self.images);
+        add_resources(&mut doc, b"XObject", xobjects);
+        let fonts = Self::embed_fonts(&mut doc, &self.fonts);
+        add_resources(&mut doc, b"Font", fonts);
+
+        let mut bytes = Vec::new();
+        doc.save_to(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    // Each image is embedded once, and returns the XObject resources that refer to them.
+    fn embed_images(doc: &mut lopdf::Document, images: &[EmbeddedImage]) -> Dictionary {
+        let mut xobjects = Dictionary::new();
+        for image in images {
+            xobjects.set(image.name.as_str(), image.image.embed(doc));
+        }
+        xobjects
+    }
+
+    // Each font is embedded once, and returns the Font resources that refer to them.
+    fn embed_fonts(doc: &mut lopdf::Document, fonts: &[EmbeddedFont]) -> Dictionary {
+        let mut font_ids = Dictionary::new();
+        for font in fonts {
+            font_ids.set(font.name.as_str(), font.embed(doc));
+        }
+        font_ids
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{
+        codecs::jpeg::JpegEncoder, ColorType, DynamicImage, GrayImage, RgbImage, Rgba, RgbaImage,
+    };
+    use taffy::prelude::{Dimension::Points, Size};
+
+    use std::collections::{BTreeSet, HashMap};
+
+    use super::*;
+    use printpdf::{Mm, Pt};
+
+    use crate::{
+        assets::Assets,
+        document::Document,
+        dom,
+        style::{ObjectFit, Style},
+    };
+
+    fn build_assets<'a>() -> Assets<'a> {
+        let mut assets = Assets::default();
+        assets.styles.add(
+            "page",
+            Style {
+                size: Size {
+                    width: Points(100.0),
+                    height: Points(100.0),
+                },
+                ..Style::DEFAULT
+            },
+        );
+        assets
+    }
+
+    fn save(assets: Assets, pages: &str) -> lopdf::Document {
+        let xml = format!("<Document>{}</Document>", pages);
+        let root = dom::parse(&xml).unwrap();
+        let output = Document::new(&root, assets).unwrap().build().unwrap();
+        lopdf::Document::load_mem(&output.save_to_bytes().unwrap()).unwrap()
+    }
+
+    // Returns ids of resources of the kind such as `XObject` on the page by their names.
+    fn get_resource_ids(
+        doc: &lopdf::Document,
+        page_id: ObjectId,
+        kind: &[u8],
+    ) -> HashMap<Vec<u8>, ObjectId> {
+        let (resources, resource_ids) = doc.get_page_resources(page_id);
+        resources
+            .into_iter()
+            .chain(
+                resource_ids
+                    .iter()
+                    .filter_map(|id| doc.get_dictionary(*id).ok()),
+            )
+            .filter_map(|resources| resources.get(kind).ok())
+            .filter_map(|objects| doc.dereference(objects).ok())
+            .filter_map(|(_, objects)| objects.as_dict().ok())
+            .flat_map(|objects| objects.iter())
+            .filter_map(|(name, object)| Some((name.clone(), object.as_reference().ok()?)))
+            .collect()
+    }
+
+    fn get_xobject_ids(doc: &lopdf::Document, page_id: ObjectId) -> HashMap<Vec<u8>, ObjectId> {
+        get_resource_ids(doc, page_id, b"XObject")
+    }
+
+    // Images are shared by pages, so each one is returned once.
+    fn get_images(doc: &lopdf::Document) -> Vec<&Dictionary> {
+        let ids: BTreeSet<ObjectId> = doc
+            .get_pages()
+            .into_values()
+            .flat_map(|page_id| get_xobject_ids(doc, page_id).into_values())
+            .collect();
+        ids.into_iter()
+            .map(|id| &doc.get_object(id).unwrap().as_stream().unwrap().dict)
+            .collect()
+    }
+
+    fn get_name<'a>(dict: &'a Dictionary, key: &[u8]) -> &'a [u8] {
+        match dict.get(key).unwrap() {
+            Object::Array(names) => names[0].as_name().unwrap(),
+            name => name.as_name().unwrap(),
+        }
+    }
+
+    #[test]
+    fn it_embeds_decoded_pixels_with_flate_decode() {
+        let mut assets = build_assets();
+        assets
+            .images
+            .add("rgb", DynamicImage::ImageRgb8(RgbImage::new(16, 16)));
+        assets
+            .images
+            .add("gray", DynamicImage::ImageLuma8(GrayImage::new(16, 16)));
+        let doc = save(
+            assets,
+            r#"<Page style="page"><Layer><Image src="rgb" /><Image src="gray" /></Layer></Page>"#,
+        );
+        let images = get_images(&doc);
+        assert_eq!(images.len(), 2);
+        for image in images {
+            assert_eq!(get_name(image, b"Filter"), b"FlateDecode");
+            assert_eq!(image.get(b"BitsPerComponent").unwrap().as_i64().unwrap(), 8);
+            assert!(!image.has(b"SMask"));
+        }
+        let mut color_spaces: Vec<&[u8]> = get_images(&doc)
+            .iter()
+            .map(|image| get_name(image, b"ColorSpace"))
+            .collect();
+        color_spaces.sort();
+        assert_eq!(color_spaces, [b"DeviceGray".as_slice(), b"DeviceRGB"]);
+    }
+
+    #[test]
+    fn it_embeds_jpeg_files_with_dct_decode() {
+        let mut bytes = Vec::new();
+        JpegEncoder::new(&mut bytes)
+            .encode(&[255; 2 * 2 * 3], 2, 2, ColorType::Rgb8)
+            .unwrap();
+        let mut assets = build_assets();
+        assets.images.add_bytes("jpeg", &bytes).unwrap();
+        let doc = save(
+            assets,
+            r#"<Page style="page"><Layer><Image src="jpeg" /></Layer></Page>"#,
+        );
+        let images = get_images(&doc);
+        assert_eq!(images.len(), 1);
+        assert_eq!(get_name(images[0], b"Filter"), b"DCTDecode");
+        assert_eq!(get_name(images[0], b"ColorSpace"), b"DeviceRGB");
+        assert_eq!(
+            images[0]
+                .get(b"BitsPerComponent")
+                .unwrap()
+                .as_i64()
+                .unwrap(),
+            8
+        );
+    }
+
+    #[test]
+    fn it_embeds_alpha_channels_as_soft_masks() {
+        let mut assets = build_assets();
+        let image = RgbaImage::from_pixel(16, 16, Rgba([0, 0, 0, 128]));
+        assets.images.add("rgba", DynamicImage::ImageRgba8(image));
+        let doc = save(
+            assets,
+            r#"<Page style="page"><Layer><Image src="rgba" /></Layer></Page>"#,
+        );
+        let images = get_images(&doc);
+        assert_eq!(images.len(), 1);
+        let smask_id = images[0].get(b"SMask").unwrap().as_reference().unwrap();
+        let smask = &doc.get_object(smask_id).unwrap().as_stream().unwrap().dict;
+        assert_eq!(get_name(smask, b"ColorSpace"), b"DeviceGray");
+        assert_eq!(smask.get(b"BitsPerComponent").unwrap().as_i64().unwrap(), 8);
+    }
+
+    #[test]
+    fn it_embeds_an_image_once_for_all_pages() {
+        let mut assets = build_assets();
+        let image = RgbaImage::from_pixel(16, 16, Rgba([0, 0, 0, 128]));
+        assets.images.add("rgba", DynamicImage::ImageRgba8(image));
+        assets
+            .images
+            .add("rgb", DynamicImage::ImageRgb8(RgbImage::new(16, 16)));
+        let doc = save(
+            assets,
+            r#"
+                <Page style="page"><Layer><Image src="rgba" /><Image src="rgb" /></Layer></Page>
+                <Page style="page"><Layer><Image src="rgb" /><Image src="rgba" /></Layer></Page>
+            "#,
+        );
+        let page_ids: Vec<ObjectId> = doc.get_pages().into_values().collect();
+        let first_ids = get_xobject_ids(&doc, page_ids[0]);
+        let second_ids = get_xobject_ids(&doc, page_ids[1]);
+        assert_eq!(first_ids.len(), 2);
+        assert_eq!(first_ids, second_ids);
+
+        let images = doc
+            .objects
+            .values()
+            .filter_map(|object| object.as_stream().ok())
+            .filter(|stream| {
+                matches!(
+                    stream.dict.get(b"Subtype").and_then(Object::as_name),
+                    Ok(b"Image")
+                )
+            })
+            .count();
+        // Two images and a soft mask.
+        assert_eq!(images, 3);
+    }
+
+    #[test]
+    fn it_draws_images_by_the_shared_names() {
+        let mut assets = build_assets();
+        assets.styles.add(
+            "image",
+            Style {
+                size: Size {
+                    width: Points(20.0),
+                    height: Points(10.0),
+                },
+                object_fit: ObjectFit::Fill,
+                ..Style::DEFAULT
+            },
+        );
+        assets
+            .images
+            .add("rgb", DynamicImage::ImageRgb8(RgbImage::new(16, 16)));
+        let doc = save(
+            assets,
+            r#"<Page style="page"><Layer><Image src="rgb" style="image" /></Layer></Page>"#,
+        );
+        let page_id = doc.get_pages()[&1];
+        let content = lopdf::content::Content::decode(&doc.get_page_content(page_id).unwrap());
+        let operations = content.unwrap().operations;
+        let index = operations
+            .iter()
+            .position(|operation| operation.operator == "Do")
+            .unwrap();
+        assert_eq!(operations[index].operands[0].as_name().unwrap(), b"Im0");
+        assert!(get_xobject_ids(&doc, page_id).contains_key(b"Im0".as_slice()));
+        // The unit square of the image is scaled to the rect.
+        let matrix = &operations[index - 1];
+        assert_eq!(matrix.operator, "cm");
+        let size: Vec<f64> = [0, 3]
+            .iter()
+            .map(|index| matrix.operands[*index].as_f64().unwrap())
+            .collect();
+        assert!((size[0] - Pt::from(Mm(20.0)).0).abs() < 0.01);
+        assert!((size[1] - Pt::from(Mm(10.0)).0).abs() < 0.01);
+    }
+
+    #[test]
+    fn it_embeds_fonts_with_the_tagged_name() {
+        let font = EmbeddedFont {
+            name: String::from("F0"),
+            base_font: String::from("ABCDEF+Test"),
+            font_file: vec![0; 16],
+            ascent: 800.0,
+            descent: -200.0,
+            bbox: [0.0, -200.0, 1000.0, 800.0],
+            glyphs: BTreeMap::from([(3, (500.0, String::from("a")))]),
+        };
+        let mut doc = lopdf::Document::with_version("1.5");
+        let type0_id = font.embed(&mut doc);
+
+        let type0_font = doc.get_dictionary(type0_id).unwrap();
+        assert_eq!(get_name(type0_font, b"BaseFont"), b"ABCDEF+Test");
+        let cid_fonts = type0_font.get(b"DescendantFonts").unwrap().as_array();
+        let cid_font = cid_fonts.unwrap()[0].as_dict().unwrap();
+        assert_eq!(get_name(cid_font, b"CIDToGIDMap"), b"Identity");
+        let descriptor_id = cid_font.get(b"FontDescriptor").unwrap();
+        let descriptor = doc
+            .get_dictionary(descriptor_id.as_reference().unwrap())
+            .unwrap();
+        assert_eq!(get_name(descriptor, b"FontName"), b"ABCDEF+Test");
+        let font_file_id = descriptor.get(b"FontFile2").unwrap().as_reference();
+        let font_file = doc.get_object(font_file_id.unwrap()).unwrap().as_stream();
+        let font_file = font_file.unwrap();
+        assert!(font_file.dict.get(b"Filter").is_err());
+        assert_eq!(
+            font_file.dict.get(b"Length1").unwrap().as_i64().unwrap(),
+            16
+        );
+    }
+}
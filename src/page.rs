@@ -2,10 +2,12 @@ use printpdf::{Mm, PdfDocumentReference};
 
 use crate::{
     assets::Assets,
+    clip::Clip,
     dom::{validate_name, Element},
     error::Error,
     layer::Layer,
     layout::LayoutTreeBuilder,
+    list::ListCounters,
 };
 
 pub struct Page<'a> {
@@ -19,8 +21,12 @@ impl<'a> Page<'a> {
         Ok(Self { element, assets })
     }
 
-    pub fn build(&self, doc: &PdfDocumentReference) -> Result<(), Box<dyn std::error::Error>> {
-        let layout_tree_builder = LayoutTreeBuilder::new(&self.assets.styles);
+    pub fn build(
+        &self,
+        doc: &PdfDocumentReference,
+        lists: &ListCounters,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let layout_tree_builder = LayoutTreeBuilder::new(&self.assets.styles, &self.assets.images);
         let layout_tree = layout_tree_builder.build(self.element)?;
 
         let size = &layout_tree.get_root_size()?;
@@ -29,7 +35,7 @@ impl<'a> Page<'a> {
 
         for node in &layout_tree.root.children {
             let layer = Layer::new(node, &layout_tree, self.assets)?;
-            layer.build(pdf_page, None)?;
+            layer.build(pdf_page, None, &Clip::default(), lists)?;
         }
         Ok(())
     }
@@ -0,0 +1,624 @@
+use std::{f64::consts::PI, str::FromStr};
+
+use lopdf::{content::Operation, Object};
+use nom::{
+    branch::alt,
+    character::complete::{char, multispace0, one_of},
+    combinator::{all_consuming, map, opt},
+    multi::many0,
+    number::complete::double,
+    sequence::{delimited, terminated},
+    IResult,
+};
+use printpdf::{Color, LineCapStyle, LineJoinStyle, Mm, PdfLayerReference, Pt, Rgb};
+
+use crate::{
+    hex_color::HexColor,
+    rect::{Rect, KAPPA},
+};
+
+fn to_pt(mm: f64) -> f64 {
+    Pt::from(Mm(mm)).0
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathSegment {
+    MoveTo(f64, f64),
+    LineTo(f64, f64),
+    CurveTo(f64, f64, f64, f64, f64, f64),
+    ClosePath,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FillRule {
+    NonZero,
+    EvenOdd,
+}
+
+// Unlike `printpdf::Line`, a path can have multiple sub paths
+// so that shapes with holes are filled correctly.
+// Coordinates are millimeters from the bottom-left of the page.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Path {
+    pub segments: Vec<PathSegment>,
+}
+
+impl Path {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn move_to(&mut self, x: f64, y: f64) {
+        self.segments.push(PathSegment::MoveTo(x, y));
+    }
+
+    pub fn line_to(&mut self, x: f64, y: f64) {
+        self.segments.push(PathSegment::LineTo(x, y));
+    }
+
+    pub fn curve_to(&mut self, x1: f64, y1: f64, x2: f64, y2: f64, x: f64, y: f64) {
+        self.segments
+            .push(PathSegment::CurveTo(x1, y1, x2, y2, x, y));
+    }
+
+    pub fn close(&mut self) {
+        self.segments.push(PathSegment::ClosePath);
+    }
+
+    pub fn from_rect(rect: &Rect) -> Self {
+        let mut path = Self::new();
+        path.move_to(rect.x, rect.y);
+        path.line_to(rect.right(), rect.y);
+        path.line_to(rect.right(), rect.bottom());
+        path.line_to(rect.x, rect.bottom());
+        path.close();
+        path
+    }
+
+    pub fn from_rounded_rect(rect: &Rect, radius: f64) -> Self {
+        let radius = radius.min(rect.width / 2.0).min(rect.height / 2.0);
+        if radius <= 0.0 {
+            return Self::from_rect(rect);
+        }
+        let k = radius * (1.0 - KAPPA);
+        let (left, top, right, bottom) = (rect.x, rect.y, rect.right(), rect.bottom());
+        let mut path = Self::new();
+        path.move_to(left + radius, top);
+        path.line_to(right - radius, top);
+        path.curve_to(right - k, top, right, top + k, right, top + radius);
+        path.line_to(right, bottom - radius);
+        path.curve_to(right, bottom - k, right - k, bottom, right - radius, bottom);
+        path.line_to(left + radius, bottom);
+        path.curve_to(left + k, bottom, left, bottom - k, left, bottom - radius);
+        path.line_to(left, top + radius);
+        path.curve_to(left, top + k, left + k, top, left + radius, top);
+        path.close();
+        path
+    }
+
+    pub fn from_ellipse(cx: f64, cy: f64, rx: f64, ry: f64) -> Self {
+        let (kx, ky) = (rx * KAPPA, ry * KAPPA);
+        let mut path = Self::new();
+        path.move_to(cx + rx, cy);
+        path.curve_to(cx + rx, cy + ky, cx + kx, cy + ry, cx, cy + ry);
+        path.curve_to(cx - kx, cy + ry, cx - rx, cy + ky, cx - rx, cy);
+        path.curve_to(cx - rx, cy - ky, cx - kx, cy - ry, cx, cy - ry);
+        path.curve_to(cx + kx, cy - ry, cx + rx, cy - ky, cx + rx, cy);
+        path.close();
+        path
+    }
+
+    // A slice of a pie chart, angles are radians from the x axis.
+    pub fn from_pie_slice(cx: f64, cy: f64, radius: f64, start: f64, end: f64) -> Self {
+        if (end - start).abs() >= 2.0 * PI - f64::EPSILON {
+            return Self::from_ellipse(cx, cy, radius, radius);
+        }
+        let mut path = Self::new();
+        path.move_to(cx, cy);
+        path.line_to(cx + radius * start.cos(), cy + radius * start.sin());
+        path.arc_to(cx, cy, radius, start, end);
+        path.close();
+        path
+    }
+
+    // Draws an arc of a circle from the current point at the start angle.
+    pub fn arc_to(&mut self, cx: f64, cy: f64, radius: f64, start: f64, end: f64) {
+        let segments = ((end - start).abs() / (PI / 2.0)).ceil().max(1.0);
+        let step = (end - start) / segments;
+        let handle = 4.0 / 3.0 * (step / 4.0).tan() * radius;
+        for index in 0..segments as usize {
+            let theta1 = start + step * index as f64;
+            let theta2 = theta1 + step;
+            let (sin1, cos1) = theta1.sin_cos();
+            let (sin2, cos2) = theta2.sin_cos();
+            self.curve_to(
+                cx + radius * cos1 - handle * sin1,
+                cy + radius * sin1 + handle * cos1,
+                cx + radius * cos2 + handle * sin2,
+                cy + radius * sin2 - handle * cos2,
+                cx + radius * cos2,
+                cy + radius * sin2,
+            );
+        }
+    }
+
+    pub fn from_polygon(points: &[(f64, f64)], is_closed: bool) -> Self {
+        let mut path = Self::new();
+        for (index, (x, y)) in points.iter().enumerate() {
+            if index == 0 {
+                path.move_to(*x, *y);
+            } else {
+                path.line_to(*x, *y);
+            }
+        }
+        if is_closed && !points.is_empty() {
+            path.close();
+        }
+        path
+    }
+
+    pub fn extend(&mut self, path: Path) {
+        self.segments.extend(path.segments);
+    }
+
+    pub fn map_points<F: Fn(f64, f64) -> (f64, f64)>(&self, map: F) -> Self {
+        let segments = self
+            .segments
+            .iter()
+            .map(|segment| match *segment {
+                PathSegment::MoveTo(x, y) => {
+                    let (x, y) = map(x, y);
+                    PathSegment::MoveTo(x, y)
+                }
+                PathSegment::LineTo(x, y) => {
+                    let (x, y) = map(x, y);
+                    PathSegment::LineTo(x, y)
+                }
+                PathSegment::CurveTo(x1, y1, x2, y2, x, y) => {
+                    let (x1, y1) = map(x1, y1);
+                    let (x2, y2) = map(x2, y2);
+                    let (x, y) = map(x, y);
+                    PathSegment::CurveTo(x1, y1, x2, y2, x, y)
+                }
+                PathSegment::ClosePath => PathSegment::ClosePath,
+            })
+            .collect();
+        Self { segments }
+    }
+
+    // The bounding box of all points including bezier control points.
+    pub fn bbox(&self) -> Option<Rect> {
+        let points: Vec<(f64, f64)> = self
+            .segments
+            .iter()
+            .flat_map(|segment| match *segment {
+                PathSegment::MoveTo(x, y) | PathSegment::LineTo(x, y) => vec![(x, y)],
+                PathSegment::CurveTo(x1, y1, x2, y2, x, y) => vec![(x1, y1), (x2, y2), (x, y)],
+                PathSegment::ClosePath => vec![],
+            })
+            .collect();
+        let (first_x, first_y) = *points.first()?;
+        let (mut left, mut top, mut right, mut bottom) = (first_x, first_y, first_x, first_y);
+        for (x, y) in points {
+            left = left.min(x);
+            right = right.max(x);
+            top = top.min(y);
+            bottom = bottom.max(y);
+        }
+        Some(Rect::new(left, top, right - left, bottom - top))
+    }
+
+    pub fn add_to_layer(&self, pdf_layer: &PdfLayerReference) {
+        for operation in self.to_operations() {
+            pdf_layer.add_operation(operation);
+        }
+    }
+
+    pub fn to_operations(&self) -> Vec<Operation> {
+        self.segments
+            .iter()
+            .map(|segment| match *segment {
+                PathSegment::MoveTo(x, y) => {
+                    Operation::new("m", vec![to_pt(x).into(), to_pt(y).into()])
+                }
+                PathSegment::LineTo(x, y) => {
+                    Operation::new("l", vec![to_pt(x).into(), to_pt(y).into()])
+                }
+                PathSegment::CurveTo(x1, y1, x2, y2, x, y) => Operation::new(
+                    "c",
+                    vec![
+                        to_pt(x1).into(),
+                        to_pt(y1).into(),
+                        to_pt(x2).into(),
+                        to_pt(y2).into(),
+                        to_pt(x).into(),
+                        to_pt(y).into(),
+                    ],
+                ),
+                PathSegment::ClosePath => Operation::new("h", vec![]),
+            })
+            .collect()
+    }
+}
+
+pub fn paint_operation(fill_rule: Option<FillRule>, has_stroke: bool) -> Operation {
+    let operator = match (fill_rule, has_stroke) {
+        (Some(FillRule::NonZero), true) => "B",
+        (Some(FillRule::EvenOdd), true) => "B*",
+        (Some(FillRule::NonZero), false) => "f",
+        (Some(FillRule::EvenOdd), false) => "f*",
+        (None, true) => "S",
+        (None, false) => "n",
+    };
+    Operation::new(operator, vec![])
+}
+
+pub fn clip_operations(fill_rule: FillRule) -> Vec<Operation> {
+    let operator = match fill_rule {
+        FillRule::NonZero => "W",
+        FillRule::EvenOdd => "W*",
+    };
+    vec![
+        Operation::new(operator, vec![]),
+        Operation::new("n", vec![]),
+    ]
+}
+
+pub fn fill_rects(pdf_layer: &PdfLayerReference, rects: &[Rect], color: Color) {
+    let mut path = Path::new();
+    for rect in rects {
+        path.extend(Path::from_rect(rect));
+    }
+    pdf_layer.set_fill_color(color);
+    path.add_to_layer(pdf_layer);
+    pdf_layer.add_operation(paint_operation(Some(FillRule::NonZero), false));
+}
+
+// Black is used if the color isn't specified.
+pub fn get_fill_color(color: Option<&str>) -> Color {
+    color
+        .map(|color| HexColor::new(color).into())
+        .unwrap_or_else(|| Color::Rgb(Rgb::new(0.0, 0.0, 0.0, None)))
+}
+
+#[derive(Debug, Clone)]
+pub struct Stroke {
+    pub color: Color,
+    pub width: f64,
+    pub cap: LineCapStyle,
+    pub join: LineJoinStyle,
+    // Dash lengths and the offset.
+    pub dash: Option<(Vec<f64>, f64)>,
+}
+
+impl Stroke {
+    // The scale is applied to the width and the dash lengths.
+    pub fn apply(&self, pdf_layer: &PdfLayerReference, scale: f64) {
+        pdf_layer.set_outline_color(self.color.clone());
+        pdf_layer.set_outline_thickness(to_pt(self.width * scale));
+        pdf_layer.set_line_cap_style(self.cap);
+        pdf_layer.set_line_join_style(self.join);
+        if let Some((dashes, offset)) = &self.dash {
+            let dashes = dashes
+                .iter()
+                .map(|dash| to_pt(dash * scale).into())
+                .collect();
+            pdf_layer.add_operation(Operation::new(
+                "d",
+                vec![Object::Array(dashes), to_pt(offset * scale).into()],
+            ));
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token {
+    Command(char),
+    Number(f64),
+}
+
+fn separator(input: &str) -> IResult<&str, Option<char>> {
+    delimited(multispace0, opt(char(',')), multispace0)(input)
+}
+
+fn tokens(input: &str) -> IResult<&str, Vec<Token>> {
+    let command = map(one_of("MmLlHhVvCcSsQqTtAaZz"), Token::Command);
+    let number = map(double, Token::Number);
+    all_consuming(delimited(
+        multispace0,
+        many0(terminated(alt((command, number)), separator)),
+        multispace0,
+    ))(input)
+}
+
+// Converts an elliptical arc to cubic bezier curves
+// that are split into segments of 90 degrees at most.
+// https://www.w3.org/TR/SVG11/implnote.html#ArcConversionEndpointToCenter
+#[allow(clippy::too_many_arguments)]
+fn arc_to_curves(
+    path: &mut Path,
+    (x1, y1): (f64, f64),
+    (rx, ry): (f64, f64),
+    angle: f64,
+    large_arc: bool,
+    sweep: bool,
+    (x2, y2): (f64, f64),
+) {
+    let (mut rx, mut ry) = (rx.abs(), ry.abs());
+    if rx == 0.0 || ry == 0.0 {
+        path.line_to(x2, y2);
+        return;
+    }
+    let (sin, cos) = angle.to_radians().sin_cos();
+    let (dx, dy) = ((x1 - x2) / 2.0, (y1 - y2) / 2.0);
+    let (x1p, y1p) = (cos * dx + sin * dy, -sin * dx + cos * dy);
+
+    // Scale up the radii when they are too small to reach the end point.
+    let lambda = (x1p * x1p) / (rx * rx) + (y1p * y1p) / (ry * ry);
+    if lambda > 1.0 {
+        rx *= lambda.sqrt();
+        ry *= lambda.sqrt();
+    }
+
+    let numerator = rx * rx * ry * ry - rx * rx * y1p * y1p - ry * ry * x1p * x1p;
+    let denominator = rx * rx * y1p * y1p + ry * ry * x1p * x1p;
+    let mut coefficient = (numerator / denominator).max(0.0).sqrt();
+    if large_arc == sweep {
+        coefficient = -coefficient;
+    }
+    let (cxp, cyp) = (coefficient * rx * y1p / ry, -coefficient * ry * x1p / rx);
+    let (cx, cy) = (
+        cos * cxp - sin * cyp + (x1 + x2) / 2.0,
+        sin * cxp + cos * cyp + (y1 + y2) / 2.0,
+    );
+
+    let vector_angle = |ux: f64, uy: f64, vx: f64, vy: f64| {
+        let sign = if ux * vy - uy * vx < 0.0 { -1.0 } else { 1.0 };
+        let dot = (ux * vx + uy * vy) / ((ux * ux + uy * uy).sqrt() * (vx * vx + vy * vy).sqrt());
+        sign * dot.clamp(-1.0, 1.0).acos()
+    };
+    let start = vector_angle(1.0, 0.0, (x1p - cxp) / rx, (y1p - cyp) / ry);
+    let mut delta = vector_angle(
+        (x1p - cxp) / rx,
+        (y1p - cyp) / ry,
+        (-x1p - cxp) / rx,
+        (-y1p - cyp) / ry,
+    );
+    if !sweep && delta > 0.0 {
+        delta -= 2.0 * PI;
+    } else if sweep && delta < 0.0 {
+        delta += 2.0 * PI;
+    }
+
+    let segments = (delta.abs() / (PI / 2.0)).ceil().max(1.0);
+    let step = delta / segments;
+    let handle = 4.0 / 3.0 * (step / 4.0).tan();
+    let point = |theta: f64| {
+        let (sin_theta, cos_theta) = theta.sin_cos();
+        (
+            cx + rx * cos_theta * cos - ry * sin_theta * sin,
+            cy + rx * cos_theta * sin + ry * sin_theta * cos,
+        )
+    };
+    let derivative = |theta: f64| {
+        let (sin_theta, cos_theta) = theta.sin_cos();
+        (
+            -rx * sin_theta * cos - ry * cos_theta * sin,
+            -rx * sin_theta * sin + ry * cos_theta * cos,
+        )
+    };
+    for index in 0..segments as usize {
+        let theta1 = start + step * index as f64;
+        let theta2 = theta1 + step;
+        let ((px1, py1), (dx1, dy1)) = (point(theta1), derivative(theta1));
+        let ((px2, py2), (dx2, dy2)) = (point(theta2), derivative(theta2));
+        path.curve_to(
+            px1 + handle * dx1,
+            py1 + handle * dy1,
+            px2 - handle * dx2,
+            py2 - handle * dy2,
+            px2,
+            py2,
+        );
+    }
+}
+
+// Parses the `d` attribute of SVG.
+// Quadratic curves and arcs are converted to cubic bezier curves.
+impl FromStr for Path {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let (_, tokens) = tokens(input).map_err(|err| err.to_string())?;
+        let mut path = Path::new();
+        let mut index = 0;
+        let mut current = None;
+        let (mut x, mut y) = (0.0, 0.0);
+        let (mut start_x, mut start_y) = (0.0, 0.0);
+        // The last control point to reflect for S and T commands.
+        let mut last_cubic: Option<(f64, f64)> = None;
+        let mut last_quad: Option<(f64, f64)> = None;
+
+        while index < tokens.len() {
+            if let Token::Command(next) = tokens[index] {
+                current = Some(next);
+                index += 1;
+            }
+            let command = current.ok_or("The path must start with a command")?;
+            let argument_count = match command.to_ascii_uppercase() {
+                'H' | 'V' => 1,
+                'M' | 'L' | 'T' => 2,
+                'S' | 'Q' => 4,
+                'C' => 6,
+                'A' => 7,
+                _ => 0,
+            };
+            let mut args = Vec::with_capacity(argument_count);
+            for _ in 0..argument_count {
+                match tokens.get(index) {
+                    Some(Token::Number(value)) => args.push(*value),
+                    _ => return Err(format!("Missing arguments for {}", command)),
+                }
+                index += 1;
+            }
+            let is_relative = command.is_ascii_lowercase();
+            let (ox, oy) = if is_relative { (x, y) } else { (0.0, 0.0) };
+
+            let (mut cubic, mut quad) = (None, None);
+            match command.to_ascii_uppercase() {
+                'M' => {
+                    (x, y) = (ox + args[0], oy + args[1]);
+                    (start_x, start_y) = (x, y);
+                    path.move_to(x, y);
+                }
+                'L' => {
+                    (x, y) = (ox + args[0], oy + args[1]);
+                    path.line_to(x, y);
+                }
+                'H' => {
+                    x = ox + args[0];
+                    path.line_to(x, y);
+                }
+                'V' => {
+                    y = oy + args[0];
+                    path.line_to(x, y);
+                }
+                'C' | 'S' => {
+                    let (x1, y1, rest) = if command.eq_ignore_ascii_case(&'C') {
+                        (ox + args[0], oy + args[1], &args[2..])
+                    } else {
+                        let (cx, cy) = last_cubic.unwrap_or((x, y));
+                        (2.0 * x - cx, 2.0 * y - cy, &args[..])
+                    };
+                    let (x2, y2) = (ox + rest[0], oy + rest[1]);
+                    (x, y) = (ox + rest[2], oy + rest[3]);
+                    path.curve_to(x1, y1, x2, y2, x, y);
+                    cubic = Some((x2, y2));
+                }
+                'Q' | 'T' => {
+                    let (qx, qy, end) = if command.eq_ignore_ascii_case(&'Q') {
+                        (ox + args[0], oy + args[1], (ox + args[2], oy + args[3]))
+                    } else {
+                        let (cx, cy) = last_quad.unwrap_or((x, y));
+                        (2.0 * x - cx, 2.0 * y - cy, (ox + args[0], oy + args[1]))
+                    };
+                    path.curve_to(
+                        x + 2.0 / 3.0 * (qx - x),
+                        y + 2.0 / 3.0 * (qy - y),
+                        end.0 + 2.0 / 3.0 * (qx - end.0),
+                        end.1 + 2.0 / 3.0 * (qy - end.1),
+                        end.0,
+                        end.1,
+                    );
+                    (x, y) = end;
+                    quad = Some((qx, qy));
+                }
+                'A' => {
+                    let end = (ox + args[5], oy + args[6]);
+                    arc_to_curves(
+                        &mut path,
+                        (x, y),
+                        (args[0], args[1]),
+                        args[2],
+                        args[3] != 0.0,
+                        args[4] != 0.0,
+                        end,
+                    );
+                    (x, y) = end;
+                }
+                _ => {
+                    path.close();
+                    (x, y) = (start_x, start_y);
+                }
+            }
+
+            (last_cubic, last_quad) = (cubic, quad);
+
+            // Coordinates after a moveto are implicit lineto commands.
+            match command {
+                'M' => current = Some('L'),
+                'm' => current = Some('l'),
+                'Z' | 'z' if matches!(tokens.get(index), Some(Token::Number(_))) => {
+                    return Err("Unexpected arguments for Z".to_string());
+                }
+                _ => {}
+            }
+        }
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_can_parse_absolute_commands() {
+        let path: Path = "M0 0 L10,0 H20 V5 Z".parse().unwrap();
+        assert_eq!(
+            path.segments,
+            vec![
+                PathSegment::MoveTo(0.0, 0.0),
+                PathSegment::LineTo(10.0, 0.0),
+                PathSegment::LineTo(20.0, 0.0),
+                PathSegment::LineTo(20.0, 5.0),
+                PathSegment::ClosePath,
+            ]
+        );
+    }
+
+    #[test]
+    fn it_can_parse_relative_commands_and_implicit_lineto() {
+        let path: Path = "m1 1 2 0 0 2 z l1 1".parse().unwrap();
+        assert_eq!(
+            path.segments,
+            vec![
+                PathSegment::MoveTo(1.0, 1.0),
+                PathSegment::LineTo(3.0, 1.0),
+                PathSegment::LineTo(3.0, 3.0),
+                PathSegment::ClosePath,
+                PathSegment::LineTo(2.0, 2.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_can_parse_negative_numbers_without_separators() {
+        let path: Path = "M10-5L-1.5-2".parse().unwrap();
+        assert_eq!(
+            path.segments,
+            vec![
+                PathSegment::MoveTo(10.0, -5.0),
+                PathSegment::LineTo(-1.5, -2.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_can_parse_arcs_as_curves() {
+        let path: Path = "M0 0 A10 10 0 0 1 20 0".parse().unwrap();
+        let ends: Vec<(f64, f64)> = path.segments[1..]
+            .iter()
+            .map(|segment| match *segment {
+                PathSegment::CurveTo(_, _, _, _, x, y) => (x.round(), y.round()),
+                _ => panic!("The arc must be converted to curves"),
+            })
+            .collect();
+        assert_eq!(ends, vec![(10.0, -10.0), (20.0, 0.0)]);
+    }
+
+    #[test]
+    fn it_cannot_parse_when_it_does_not_start_with_a_command() {
+        assert!("0 0 L10 10".parse::<Path>().is_err());
+    }
+
+    #[test]
+    fn it_cannot_parse_when_arguments_are_missing() {
+        assert!("M0 0 L10".parse::<Path>().is_err());
+    }
+
+    #[test]
+    fn it_cannot_parse_unknown_commands() {
+        assert!("M0 0 X10 10".parse::<Path>().is_err());
+    }
+}
@@ -0,0 +1,99 @@
+use printpdf::PdfPageReference;
+use qrcode::EcLevel;
+
+use crate::{
+    clip::Clip,
+    dom::{get_attr, get_required_attr, validate_name},
+    error::Error,
+    layout::{LayoutNode, LayoutTree},
+    path::{fill_rects, get_fill_color},
+    rect::Rect,
+};
+
+// A quiet zone of 4 modules is required around the symbol.
+const QUIET_ZONE: usize = 4;
+
+pub struct QrCode<'a> {
+    node: &'a LayoutNode<'a>,
+    layout_tree: &'a LayoutTree<'a>,
+}
+
+impl<'a> QrCode<'a> {
+    pub fn new(node: &'a LayoutNode, layout_tree: &'a LayoutTree) -> Result<Self, Error> {
+        validate_name(node.element, "QrCode")?;
+        Ok(Self { node, layout_tree })
+    }
+
+    pub fn build(
+        &self,
+        pdf_page: &PdfPageReference,
+        parent_rect: Option<&Rect>,
+        clip: &Clip,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let rect = self
+            .layout_tree
+            .build_absolute_rect(self.node, parent_rect)?;
+        let rect = self.layout_tree.build_bottom_left_base_rect(&rect)?;
+        self.draw(pdf_page, &rect, clip)?;
+        Ok(())
+    }
+
+    fn get_ec_level(&self) -> Result<EcLevel, Error> {
+        let element = self.node.element;
+        match get_attr::<String>(element, "ecc").as_deref() {
+            Some("L") => Ok(EcLevel::L),
+            None | Some("M") => Ok(EcLevel::M),
+            Some("Q") => Ok(EcLevel::Q),
+            Some("H") => Ok(EcLevel::H),
+            _ => Err(Error::InvalidAttribute(
+                element.name.to_string(),
+                "ecc".to_string(),
+            )),
+        }
+    }
+
+    // The symbol is drawn as a square in the center of the rect,
+    // and dark modules in a row are merged into one rect.
+    fn draw(&self, pdf_page: &PdfPageReference, rect: &Rect, clip: &Clip) -> Result<(), Error> {
+        let element = self.node.element;
+        let data: String = get_required_attr(element, "data")?;
+        let code = qrcode::QrCode::with_error_correction_level(data, self.get_ec_level()?)
+            .map_err(|_| Error::InvalidAttribute(element.name.to_string(), "data".to_string()))?;
+        let width = code.width();
+        let colors = code.to_colors();
+
+        let size = rect.width.min(rect.height);
+        let module_size = size / (width + QUIET_ZONE * 2) as f64;
+        let left = rect.x + (rect.width - size) / 2.0 + module_size * QUIET_ZONE as f64;
+        let top = rect.y + (rect.height + size) / 2.0 - module_size * QUIET_ZONE as f64;
+
+        let mut rects = vec![];
+        for (row, modules) in colors.chunks(width).enumerate() {
+            let mut column = 0;
+            while column < width {
+                if modules[column] != qrcode::Color::Dark {
+                    column += 1;
+                    continue;
+                }
+                let start = column;
+                while column < width && modules[column] == qrcode::Color::Dark {
+                    column += 1;
+                }
+                rects.push(Rect::new(
+                    left + module_size * start as f64,
+                    top - module_size * (row + 1) as f64,
+                    module_size * (column - start) as f64,
+                    module_size,
+                ));
+            }
+        }
+
+        let pdf_layer = clip.add_layer(pdf_page);
+        fill_rects(
+            &pdf_layer,
+            &rects,
+            get_fill_color(self.node.style.fill_color),
+        );
+        Ok(())
+    }
+}
@@ -1,6 +1,10 @@
 use printpdf::{Line, Mm, Point};
 
-#[derive(Debug)]
+// The ratio of the distance to bezier control points for approximating
+// a quarter of a circle.
+pub const KAPPA: f64 = 0.552_284_749_8;
+
+#[derive(Debug, Clone)]
 pub struct Rect {
     pub x: f64,
     pub y: f64,
@@ -43,9 +47,42 @@ impl Rect {
         ]
     }
 
+    pub fn to_rounded_points(&self, radius: f64) -> Vec<(Point, bool)> {
+        let radius = radius.min(self.width / 2.0).min(self.height / 2.0);
+        if radius <= 0.0 {
+            return self.to_points();
+        }
+        let k = radius * KAPPA;
+        let (left, right, bottom, top) = (self.x, self.right(), self.y, self.bottom());
+        let point = |x: f64, y: f64, is_bezier: bool| (Point::new(Mm(x), Mm(y)), is_bezier);
+        vec![
+            point(left + radius, bottom, false),
+            point(right - radius, bottom, true),
+            point(right - radius + k, bottom, true),
+            point(right, bottom + radius - k, false),
+            point(right, bottom + radius, false),
+            point(right, top - radius, true),
+            point(right, top - radius + k, true),
+            point(right - radius + k, top, false),
+            point(right - radius, top, false),
+            point(left + radius, top, true),
+            point(left + radius - k, top, true),
+            point(left, top - radius + k, false),
+            point(left, top - radius, false),
+            point(left, bottom + radius, true),
+            point(left, bottom + radius - k, true),
+            point(left + radius - k, bottom, false),
+            point(left + radius, bottom, false),
+        ]
+    }
+
     pub fn to_line(&self) -> Line {
+        self.to_rounded_line(0.0)
+    }
+
+    pub fn to_rounded_line(&self, radius: f64) -> Line {
         Line {
-            points: self.to_points(),
+            points: self.to_rounded_points(radius),
             is_closed: true,
             has_fill: true,
             has_stroke: true,
@@ -53,10 +90,30 @@ impl Rect {
         }
     }
 
+    // Printpdf only treats a line as a clipping path when it has neither fill nor stroke.
+    pub fn to_clipping_line(&self, radius: f64) -> Line {
+        Line {
+            points: self.to_rounded_points(radius),
+            is_closed: true,
+            has_fill: false,
+            has_stroke: false,
+            is_clipping_path: true,
+        }
+    }
+
     pub fn set_height(&self, height: f64) -> Self {
         Self::new(self.x, self.y, self.width, height)
     }
 
+    pub fn inset(&self, left: f64, top: f64, right: f64, bottom: f64) -> Self {
+        Self::new(
+            self.x + left,
+            self.y + top,
+            (self.width - left - right).max(0.0),
+            (self.height - top - bottom).max(0.0),
+        )
+    }
+
     pub fn right(&self) -> f64 {
         self.x + self.width
     }
@@ -65,3 +122,71 @@ impl Rect {
         self.y + self.height
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_mm(points: &[(Point, bool)]) -> Vec<(f64, f64, bool)> {
+        points
+            .iter()
+            .map(|(point, is_bezier)| {
+                (
+                    Mm::from(point.x).0.round(),
+                    Mm::from(point.y).0.round(),
+                    *is_bezier,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn it_rounds_corners_of_the_clipping_path() {
+        let line = Rect::new(10.0, 20.0, 40.0, 30.0).to_clipping_line(5.0);
+        assert!(line.is_clipping_path && !line.has_fill && !line.has_stroke);
+        let points = to_mm(&line.points);
+        assert_eq!(points.len(), 17);
+        assert_eq!(points[0], (15.0, 20.0, false));
+        assert_eq!(points[1], (45.0, 20.0, true));
+        assert_eq!(points[4], (50.0, 25.0, false));
+        assert_eq!(points[8], (45.0, 50.0, false));
+        assert_eq!(points[12], (10.0, 45.0, false));
+        assert_eq!(points[16], points[0]);
+    }
+
+    #[test]
+    fn it_limits_the_radius_to_half_of_the_shorter_side() {
+        let points = to_mm(&Rect::new(0.0, 0.0, 40.0, 10.0).to_rounded_points(50.0));
+        assert_eq!(points[0], (5.0, 0.0, false));
+        assert_eq!(points[4], (40.0, 5.0, false));
+    }
+
+    #[test]
+    fn it_uses_sharp_corners_without_a_radius() {
+        let line = Rect::new(0.0, 0.0, 40.0, 10.0).to_clipping_line(0.0);
+        assert_eq!(
+            to_mm(&line.points),
+            vec![
+                (0.0, 0.0, false),
+                (40.0, 0.0, false),
+                (40.0, 10.0, false),
+                (0.0, 10.0, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_insets_each_side() {
+        let rect = Rect::new(10.0, 20.0, 40.0, 30.0).inset(1.0, 2.0, 3.0, 4.0);
+        assert_eq!(
+            (rect.x, rect.y, rect.width, rect.height),
+            (11.0, 22.0, 36.0, 24.0)
+        );
+    }
+
+    #[test]
+    fn it_keeps_inset_sizes_non_negative() {
+        let rect = Rect::new(0.0, 0.0, 4.0, 4.0).inset(3.0, 3.0, 3.0, 3.0);
+        assert_eq!((rect.width, rect.height), (0.0, 0.0));
+    }
+}
@@ -0,0 +1,148 @@
+use std::str::FromStr;
+
+use printpdf::PdfPageReference;
+
+use crate::{
+    clip::Clip,
+    dom::{get_attr, get_required_attr, parse_required_attr},
+    error::Error,
+    hex_color::HexColor,
+    layout::{LayoutNode, LayoutTree},
+    path::{paint_operation, FillRule, Path, Stroke},
+    rect::Rect,
+};
+
+// The `points` attribute of `<Polygon>` same as SVG, e.g. "0,0 10,0 5,8".
+struct Points(Vec<(f64, f64)>);
+
+impl FromStr for Points {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let values = input
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|value| !value.is_empty())
+            .map(|value| value.parse::<f64>().map_err(|err| err.to_string()))
+            .collect::<Result<Vec<_>, _>>()?;
+        if values.len() % 2 != 0 {
+            return Err("The points must be pairs of x and y".to_string());
+        }
+        Ok(Self(
+            values.chunks(2).map(|point| (point[0], point[1])).collect(),
+        ))
+    }
+}
+
+pub struct Shape<'a> {
+    node: &'a LayoutNode<'a>,
+    layout_tree: &'a LayoutTree<'a>,
+}
+
+impl<'a> Shape<'a> {
+    pub const NAMES: [&'static str; 5] = ["Line", "Rectangle", "Ellipse", "Polygon", "Path"];
+
+    pub fn new(node: &'a LayoutNode, layout_tree: &'a LayoutTree) -> Result<Self, Error> {
+        if !Self::NAMES.contains(&node.element.name.as_str()) {
+            return Err(Error::NotSupportElement(
+                node.element.name.to_string(),
+                "Shape".to_string(),
+            ));
+        }
+        Ok(Self { node, layout_tree })
+    }
+
+    pub fn build(
+        &self,
+        pdf_page: &PdfPageReference,
+        parent_rect: Option<&Rect>,
+        clip: &Clip,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let rect = self
+            .layout_tree
+            .build_absolute_rect(self.node, parent_rect)?;
+        let path = self.build_path(&rect)?;
+
+        // The geometry is given from the top-left of the element,
+        // so it's moved to the position on the page.
+        let rect = self.layout_tree.build_bottom_left_base_rect(&rect)?;
+        let path = path.map_points(|x, y| (rect.x + x, rect.y + rect.height - y));
+        self.draw(pdf_page, &path, clip);
+        Ok(())
+    }
+
+    fn build_path(&self, rect: &Rect) -> Result<Path, Error> {
+        let element = self.node.element;
+        match element.name.as_str() {
+            "Line" => {
+                let start: (f64, f64) = (
+                    get_required_attr(element, "x1")?,
+                    get_required_attr(element, "y1")?,
+                );
+                let end: (f64, f64) = (
+                    get_required_attr(element, "x2")?,
+                    get_required_attr(element, "y2")?,
+                );
+                Ok(Path::from_polygon(&[start, end], false))
+            }
+            "Rectangle" => {
+                let rect = Rect::new(
+                    get_attr(element, "x").unwrap_or(0.0),
+                    get_attr(element, "y").unwrap_or(0.0),
+                    get_attr(element, "width").unwrap_or(rect.width),
+                    get_attr(element, "height").unwrap_or(rect.height),
+                );
+                let radius = get_attr(element, "rx").unwrap_or(0.0);
+                Ok(Path::from_rounded_rect(&rect, radius))
+            }
+            "Ellipse" => Ok(Path::from_ellipse(
+                get_attr(element, "cx").unwrap_or(rect.width / 2.0),
+                get_attr(element, "cy").unwrap_or(rect.height / 2.0),
+                get_attr(element, "rx").unwrap_or(rect.width / 2.0),
+                get_attr(element, "ry").unwrap_or(rect.height / 2.0),
+            )),
+            "Polygon" => {
+                let points: Points = parse_required_attr(element, "points")?;
+                Ok(Path::from_polygon(&points.0, true))
+            }
+            _ => parse_required_attr(element, "d"),
+        }
+    }
+
+    fn build_stroke(&self) -> Option<Stroke> {
+        let style = self.node.style;
+        let dash = if style.stroke_dasharray.is_empty() {
+            None
+        } else {
+            Some((style.stroke_dasharray.to_vec(), 0.0))
+        };
+        style.stroke_color.map(|stroke_color| Stroke {
+            color: HexColor::new(stroke_color).into(),
+            width: style.stroke_width,
+            cap: style.stroke_line_cap.into(),
+            join: style.stroke_line_join.into(),
+            dash,
+        })
+    }
+
+    fn draw(&self, pdf_page: &PdfPageReference, path: &Path, clip: &Clip) {
+        let pdf_layer = clip.add_layer(pdf_page);
+        let stroke = self.build_stroke();
+        if let Some(stroke) = &stroke {
+            stroke.apply(&pdf_layer, 1.0);
+        }
+        // A line has no area to fill.
+        let fill_color = self
+            .node
+            .style
+            .fill_color
+            .filter(|_| self.node.element.name != "Line");
+        if let Some(fill_color) = fill_color {
+            pdf_layer.set_fill_color(HexColor::new(fill_color).into());
+        }
+        path.add_to_layer(&pdf_layer);
+        pdf_layer.add_operation(paint_operation(
+            fill_color.map(|_| FillRule::NonZero),
+            stroke.is_some(),
+        ));
+    }
+}
@@ -0,0 +1,299 @@
+use std::{ops::Range, str::FromStr};
+
+use lopdf::{content::Operation, Object, StringFormat};
+use rustybuzz::{Direction, Face, Feature, UnicodeBuffer};
+use ttf_parser::GlyphId;
+use unicode_bidi::BidiInfo;
+
+use crate::error::Error;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Glyph {
+    pub id: u16,
+    // The byte index of the first character that the glyph is made from.
+    pub cluster: usize,
+    // Sizes are in em.
+    // The width is the advance of the glyph itself, it's written in the PDF.
+    pub width: f64,
+    pub x_advance: f64,
+    pub x_offset: f64,
+    pub y_offset: f64,
+}
+
+// Features are written same as harfbuzz, e.g. `-liga` or `ss01=1`.
+pub fn parse_features(settings: &[&str]) -> Result<Vec<Feature>, Error> {
+    settings
+        .iter()
+        .map(|setting| {
+            Feature::from_str(setting.trim())
+                .map_err(|_| Error::InvalidFontFeature(setting.to_string()))
+        })
+        .collect()
+}
+
+// Kerning and standard ligatures are applied by default.
+// Glyphs of a right-to-left text are returned in visual order.
+pub fn shape(face: &Face, text: &str, features: &[Feature], is_rtl: bool) -> Vec<Glyph> {
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str(text);
+    buffer.set_direction(if is_rtl {
+        Direction::RightToLeft
+    } else {
+        Direction::LeftToRight
+    });
+    let output = rustybuzz::shape(face, features, buffer);
+    let units_per_em = f64::from(face.units_per_em());
+    let to_em = |value: i32| f64::from(value) / units_per_em;
+
+    output
+        .glyph_infos()
+        .iter()
+        .zip(output.glyph_positions())
+        .map(|(info, position)| {
+            let id = info.glyph_id as u16;
+            Glyph {
+                id,
+                cluster: info.cluster as usize,
+                width: to_em(face.glyph_hor_advance(GlyphId(id)).unwrap_or(0).into()),
+                x_advance: to_em(position.x_advance),
+                x_offset: to_em(position.x_offset),
+                y_offset: to_em(position.y_offset),
+            }
+        })
+        .collect()
+}
+
+// Splits the line into runs in visual order by the Unicode Bidirectional Algorithm.
+// Each run is a range of bytes of the text and whether it's right-to-left.
+pub fn reorder_line(bidi_info: &BidiInfo, line: Range<usize>) -> Vec<(Range<usize>, bool)> {
+    let mut runs = Vec::new();
+    for paragraph in &bidi_info.paragraphs {
+        let start = line.start.max(paragraph.range.start);
+        let end = line.end.min(paragraph.range.end);
+        if start >= end {
+            continue;
+        }
+        let (levels, level_runs) = bidi_info.visual_runs(paragraph, start..end);
+        for run in level_runs {
+            let is_rtl = levels[run.start].is_rtl();
+            runs.push((run, is_rtl));
+        }
+    }
+    runs
+}
+
+// Returns the text that each glyph is made from to copy texts from the PDF.
+// The text of a cluster is given to the first glyph of it, e.g. "fi" of a ligature.
+pub fn get_glyph_texts<'b>(glyphs: &[Glyph], text: &'b str) -> Vec<(u16, &'b str)> {
+    let mut clusters: Vec<usize> = glyphs.iter().map(|glyph| glyph.cluster).collect();
+    clusters.sort_unstable();
+    clusters.dedup();
+
+    let mut used_clusters = Vec::new();
+    glyphs
+        .iter()
+        .map(|glyph| {
+            if used_clusters.contains(&glyph.cluster) {
+                return (glyph.id, "");
+            }
+            used_clusters.push(glyph.cluster);
+            let end = clusters
+                .iter()
+                .find(|cluster| **cluster > glyph.cluster)
+                .copied()
+                .unwrap_or(text.len());
+            (glyph.id, &text[glyph.cluster..end])
+        })
+        .collect()
+}
+
+// Splits glyphs into clusters, and glyphs in each cluster are made from the same characters.
+pub fn split_clusters(glyphs: &[Glyph]) -> Vec<&[Glyph]> {
+    let mut clusters = Vec::new();
+    let mut start = 0;
+    for index in 1..=glyphs.len() {
+        if index == glyphs.len() || glyphs[index].cluster != glyphs[start].cluster {
+            clusters.push(&glyphs[start..index]);
+            start = index;
+        }
+    }
+    clusters
+}
+
+// PDF viewers move to the next glyph by its width,
+// so the difference from the shaped advance is adjusted by numbers in `TJ`.
+// Vertical offsets are written as the text rise.
+pub fn build_text_operations(glyphs: &[Glyph], font_size: f64) -> Vec<Operation> {
+    let mut operations = Vec::new();
+    let mut items: Vec<Object> = Vec::new();
+    let mut ids: Vec<u8> = Vec::new();
+    let mut adjustment = 0.0;
+    let mut rise = 0.0;
+
+    let flush_ids = |items: &mut Vec<Object>, ids: &mut Vec<u8>| {
+        if !ids.is_empty() {
+            items.push(Object::String(
+                std::mem::take(ids),
+                StringFormat::Hexadecimal,
+            ));
+        }
+    };
+    let flush_adjustment = |items: &mut Vec<Object>, ids: &mut Vec<u8>, adjustment: &mut f64| {
+        // Adjustments smaller than 1/1000 em can't be seen.
+        if adjustment.abs() >= 0.001 {
+            flush_ids(items, ids);
+            items.push((*adjustment * -1000.0).into());
+        }
+        *adjustment = 0.0;
+    };
+
+    for glyph in glyphs {
+        if glyph.y_offset != rise {
+            flush_adjustment(&mut items, &mut ids, &mut adjustment);
+            flush_ids(&mut items, &mut ids);
+            if !items.is_empty() {
+                operations.push(Operation::new(
+                    "TJ",
+                    vec![Object::Array(items.split_off(0))],
+                ));
+            }
+            rise = glyph.y_offset;
+            operations.push(Operation::new("Ts", vec![(rise * font_size).into()]));
+        }
+        adjustment += glyph.x_offset;
+        flush_adjustment(&mut items, &mut ids, &mut adjustment);
+        ids.extend_from_slice(&glyph.id.to_be_bytes());
+        adjustment = glyph.x_advance - glyph.width - glyph.x_offset;
+    }
+    flush_adjustment(&mut items, &mut ids, &mut adjustment);
+    flush_ids(&mut items, &mut ids);
+    if !items.is_empty() {
+        operations.push(Operation::new("TJ", vec![Object::Array(items)]));
+    }
+    if rise != 0.0 {
+        operations.push(Operation::new("Ts", vec![0.0.into()]));
+    }
+    operations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use unicode_bidi::Level;
+
+    fn glyph(id: u16, cluster: usize, width: f64, x_advance: f64) -> Glyph {
+        Glyph {
+            id,
+            cluster,
+            width,
+            x_advance,
+            x_offset: 0.0,
+            y_offset: 0.0,
+        }
+    }
+
+    fn reorder(text: &str, level: Level) -> Vec<(&str, bool)> {
+        let bidi_info = BidiInfo::new(text, Some(level));
+        reorder_line(&bidi_info, 0..text.len())
+            .into_iter()
+            .map(|(range, is_rtl)| (&text[range], is_rtl))
+            .collect()
+    }
+
+    #[test]
+    fn it_keeps_ltr_texts_in_one_run() {
+        assert_eq!(reorder("abc def", Level::ltr()), vec![("abc def", false)]);
+    }
+
+    #[test]
+    fn it_reorders_rtl_words_in_ltr_paragraphs() {
+        assert_eq!(
+            reorder("ab \u{5e9}\u{5dc}\u{5d5}\u{5dd} cd", Level::ltr()),
+            vec![
+                ("ab ", false),
+                ("\u{5e9}\u{5dc}\u{5d5}\u{5dd}", true),
+                (" cd", false)
+            ]
+        );
+    }
+
+    #[test]
+    fn it_reorders_ltr_words_in_rtl_paragraphs() {
+        assert_eq!(
+            reorder("\u{5e9}\u{5dc} ab \u{5d5}\u{5dd}", Level::rtl()),
+            vec![
+                (" \u{5d5}\u{5dd}", true),
+                ("ab", false),
+                ("\u{5e9}\u{5dc} ", true)
+            ]
+        );
+    }
+
+    #[test]
+    fn it_parses_features() {
+        let features = parse_features(&["-liga", "kern=0", "+smcp"]).unwrap();
+        assert_eq!(
+            features.iter().map(|f| f.value).collect::<Vec<_>>(),
+            vec![0, 0, 1]
+        );
+    }
+
+    #[test]
+    fn it_cannot_parse_invalid_features() {
+        assert!(parse_features(&["liga=x"]).is_err());
+    }
+
+    #[test]
+    fn it_gives_texts_of_ligatures_to_the_glyph() {
+        let glyphs = [glyph(1, 0, 0.5, 0.5), glyph(2, 2, 0.5, 0.5)];
+        assert_eq!(get_glyph_texts(&glyphs, "fix"), vec![(1, "fi"), (2, "x")]);
+    }
+
+    #[test]
+    fn it_gives_texts_of_decomposed_clusters_to_the_first_glyph() {
+        let glyphs = [glyph(1, 0, 0.5, 0.5), glyph(2, 0, 0.5, 0.5)];
+        assert_eq!(get_glyph_texts(&glyphs, "é"), vec![(1, "é"), (2, "")]);
+    }
+
+    #[test]
+    fn it_writes_glyphs_without_adjustments() {
+        let operations =
+            build_text_operations(&[glyph(1, 0, 0.5, 0.5), glyph(258, 1, 0.5, 0.5)], 10.0);
+        assert_eq!(operations.len(), 1);
+        assert_eq!(operations[0].operator, "TJ");
+        let items = operations[0].operands[0].as_array().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].as_str().unwrap(), [0, 1, 1, 2]);
+    }
+
+    #[test]
+    fn it_adjusts_kerning() {
+        let operations =
+            build_text_operations(&[glyph(1, 0, 0.5, 0.4), glyph(2, 1, 0.5, 0.5)], 10.0);
+        let items = operations[0].operands[0].as_array().unwrap();
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[1].as_f64().unwrap().round(), 100.0);
+    }
+
+    #[test]
+    fn it_raises_glyphs_with_vertical_offsets() {
+        let mut mark = glyph(2, 1, 0.0, 0.0);
+        mark.y_offset = 0.2;
+        let operations = build_text_operations(&[glyph(1, 0, 0.5, 0.5), mark], 10.0);
+        let operators: Vec<&str> = operations.iter().map(|o| o.operator.as_str()).collect();
+        assert_eq!(operators, vec!["TJ", "Ts", "TJ", "Ts"]);
+        assert_eq!(operations[1].operands[0].as_f64().unwrap(), 2.0);
+    }
+
+    #[test]
+    fn it_splits_glyphs_into_clusters() {
+        let glyphs = [
+            glyph(1, 0, 1.0, 1.0),
+            glyph(2, 0, 0.0, 0.0),
+            glyph(3, 3, 1.0, 1.0),
+        ];
+        let clusters = split_clusters(&glyphs);
+        assert_eq!(clusters, vec![&glyphs[0..2], &glyphs[2..3]]);
+        assert!(split_clusters(&[]).is_empty());
+    }
+}
@@ -1,3 +1,4 @@
+use printpdf::{LineCapStyle, LineJoinStyle};
 use taffy::{
     prelude::{Rect, Size},
     style::{
@@ -6,6 +7,153 @@ use taffy::{
     },
 };
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    Visible,
+    Hidden,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectFit {
+    Contain,
+    Cover,
+    Fill,
+    None,
+    ScaleDown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineCap {
+    Butt,
+    Round,
+    Square,
+}
+
+impl From<LineCap> for LineCapStyle {
+    fn from(item: LineCap) -> Self {
+        match item {
+            LineCap::Butt => LineCapStyle::Butt,
+            LineCap::Round => LineCapStyle::Round,
+            LineCap::Square => LineCapStyle::ProjectingSquare,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineJoin {
+    Miter,
+    Round,
+    Bevel,
+}
+
+impl From<LineJoin> for LineJoinStyle {
+    fn from(item: LineJoin) -> Self {
+        match item {
+            LineJoin::Miter => LineJoinStyle::Miter,
+            LineJoin::Round => LineJoinStyle::Round,
+            LineJoin::Bevel => LineJoinStyle::Limit,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FontStyle {
+    Normal,
+    Italic,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Ltr,
+    Rtl,
+}
+
+// `Start` and `End` are resolved by `direction` same as CSS.
+// In `Justify`, lines are broken to minimize the unevenness of the paragraph
+// and spaces are stretched to fill lines except the last one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextAlign {
+    Start,
+    End,
+    Left,
+    Right,
+    Center,
+    Justify,
+}
+
+// Same as CSS, `Pre` keeps whitespaces and newlines without wrapping lines,
+// and `PreWrap` keeps them with wrapping lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WhiteSpace {
+    Normal,
+    Pre,
+    PreWrap,
+}
+
+// Same as CSS, `Manual` hyphenates words only at soft hyphens (U+00AD),
+// and `Auto` also uses patterns of the language given by the `lang` attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hyphens {
+    None,
+    Manual,
+    Auto,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextOverflow {
+    Clip,
+    Ellipsis,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WritingMode {
+    HorizontalTb,
+    VerticalRl,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ObjectPosition {
+    pub x: Dimension,
+    pub y: Dimension,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TabAlign {
+    Left,
+    Right,
+    Center,
+    // The first period of the text after the tab is aligned to the stop.
+    Decimal,
+}
+
+// A tab stop at the position in millimeters from the start of the line.
+// The gap before the text is filled with the leader character if it's given, e.g. `Some('.')`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TabStop {
+    pub position: f64,
+    pub align: TabAlign,
+    pub leader: Option<char>,
+}
+
+// Bounds of font sizes in points to search the largest one that fits in the box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FontSizeFit {
+    pub min: f64,
+    pub max: f64,
+}
+
+impl ObjectPosition {
+    pub const TOP_LEFT: ObjectPosition = ObjectPosition {
+        x: Dimension::Percent(0.0),
+        y: Dimension::Percent(0.0),
+    };
+
+    pub const CENTER: ObjectPosition = ObjectPosition {
+        x: Dimension::Percent(0.5),
+        y: Dimension::Percent(0.5),
+    };
+}
+
 #[derive(Debug)]
 pub struct Style<'a> {
     pub display: Display,
@@ -30,9 +178,43 @@ pub struct Style<'a> {
     pub aspect_ratio: Option<f32>,
     pub background_color: Option<&'a str>,
     pub border_color: Option<&'a str>,
+    pub border_radius: f64,
+    pub overflow: Overflow,
+    pub object_fit: ObjectFit,
+    pub object_position: ObjectPosition,
+    pub fill_color: Option<&'a str>,
+    pub stroke_color: Option<&'a str>,
+    pub stroke_width: f64,
+    pub stroke_line_cap: LineCap,
+    pub stroke_line_join: LineJoin,
+    pub stroke_dasharray: &'a [f64],
     pub font_size: f64,
+    // When it's given, `<Text>` uses the largest font size in the bounds whose lines fit
+    // in the box instead of `font_size`, and `line_height` is scaled by the same ratio.
+    pub font_size_fit: Option<FontSizeFit>,
     pub font_family: &'a str,
+    // Same as CSS, 400 is normal and 700 is bold.
+    pub font_weight: u16,
+    pub font_style: FontStyle,
+    // Features of `<Text>` same as harfbuzz, e.g. `&["-liga", "smcp"]`.
+    // Kerning and standard ligatures are on by default.
+    pub font_feature_settings: &'a [&'a str],
     pub line_height: f64,
+    // The base direction of paragraphs of `<Text>`.
+    pub direction: Direction,
+    pub text_align: TextAlign,
+    // In `VerticalRl`, lines are wrapped by the height and progress from right to left.
+    pub writing_mode: WritingMode,
+    pub white_space: WhiteSpace,
+    // Tabs of `<Text>` are kept in `Pre` and `PreWrap`, and they advance to these stops.
+    // Without stops, they advance to multiples of 8 spaces.
+    pub tab_stops: &'a [TabStop],
+    pub hyphens: Hyphens,
+    // Lines of `<Text>` beyond this are not written.
+    pub max_lines: Option<usize>,
+    // In `Ellipsis`, the last visible line ends with "…" if the text is truncated
+    // by `max_lines` or the size of the box.
+    pub text_overflow: TextOverflow,
 }
 
 impl<'a> From<&Style<'a>> for taffy::style::Style {
@@ -63,7 +245,7 @@ impl<'a> From<&Style<'a>> for taffy::style::Style {
 }
 
 impl<'a> Style<'a> {
-    const DEFAULT_FONT_FAMILY: &str = "default";
+    const DEFAULT_FONT_FAMILY: &'static str = "default";
 
     pub const DEFAULT: Style<'a> = Style {
         display: Display::Flex,
@@ -88,8 +270,30 @@ impl<'a> Style<'a> {
         aspect_ratio: None,
         background_color: None,
         border_color: None,
+        border_radius: 0.0,
+        overflow: Overflow::Visible,
+        object_fit: ObjectFit::Contain,
+        object_position: ObjectPosition::TOP_LEFT,
+        fill_color: None,
+        stroke_color: None,
+        stroke_width: 0.3,
+        stroke_line_cap: LineCap::Butt,
+        stroke_line_join: LineJoin::Miter,
+        stroke_dasharray: &[],
         font_size: 14.0,
+        font_size_fit: None,
         line_height: 16.0,
         font_family: Self::DEFAULT_FONT_FAMILY,
+        font_weight: 400,
+        font_style: FontStyle::Normal,
+        font_feature_settings: &[],
+        direction: Direction::Ltr,
+        text_align: TextAlign::Start,
+        writing_mode: WritingMode::HorizontalTb,
+        white_space: WhiteSpace::Normal,
+        tab_stops: &[],
+        hyphens: Hyphens::Manual,
+        max_lines: None,
+        text_overflow: TextOverflow::Clip,
     };
 }
@@ -0,0 +1,284 @@
+use std::collections::BTreeSet;
+
+// Printpdf writes texts as glyph ids, so the subset keeps the glyph ids
+// and empties the outlines of unused glyphs instead of renumbering them.
+// Only TrueType outlines are supported, CFF fonts are embedded as they are.
+// https://learn.microsoft.com/en-us/typography/opentype/spec/otff
+
+// The other tables such as GSUB and GPOS aren't used by PDF viewers.
+const KEPT_TABLES: [&[u8; 4]; 14] = [
+    b"OS/2", b"cmap", b"cvt ", b"fpgm", b"gasp", b"glyf", b"head", b"hhea", b"hmtx", b"loca",
+    b"maxp", b"name", b"post", b"prep",
+];
+
+const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+const WE_HAVE_A_SCALE: u16 = 0x0008;
+const MORE_COMPONENTS: u16 = 0x0020;
+const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn find_table<'a>(font: &'a [u8], tag: &[u8; 4]) -> Option<&'a [u8]> {
+    let num_tables = read_u16(font, 4)? as usize;
+    (0..num_tables).find_map(|index| {
+        let record = 12 + index * 16;
+        if font.get(record..record + 4)? != tag {
+            return None;
+        }
+        let offset = read_u32(font, record + 8)? as usize;
+        let length = read_u32(font, record + 12)? as usize;
+        font.get(offset..offset + length)
+    })
+}
+
+fn compute_checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+fn read_loca(loca: &[u8], num_glyphs: usize, is_long: bool) -> Option<Vec<usize>> {
+    (0..=num_glyphs)
+        .map(|index| {
+            if is_long {
+                read_u32(loca, index * 4).map(|offset| offset as usize)
+            } else {
+                read_u16(loca, index * 2).map(|offset| offset as usize * 2)
+            }
+        })
+        .collect()
+}
+
+// Returns the glyph ids that a composite glyph is made of.
+fn read_components(glyph: &[u8]) -> Option<Vec<u16>> {
+    let mut components = Vec::new();
+    if glyph.len() < 10 || (read_u16(glyph, 0)? as i16) >= 0 {
+        return Some(components);
+    }
+    let mut offset = 10;
+    loop {
+        let flags = read_u16(glyph, offset)?;
+        components.push(read_u16(glyph, offset + 2)?);
+        offset += if flags & ARG_1_AND_2_ARE_WORDS != 0 {
+            8
+        } else {
+            6
+        };
+        if flags & WE_HAVE_A_SCALE != 0 {
+            offset += 2;
+        } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+            offset += 4;
+        } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+            offset += 8;
+        }
+        if flags & MORE_COMPONENTS == 0 {
+            return Some(components);
+        }
+    }
+}
+
+pub fn build_font(tables: &[(&[u8; 4], Vec<u8>)]) -> Vec<u8> {
+    let num_tables = tables.len() as u16;
+    let entry_selector = 15 - num_tables.leading_zeros() as u16;
+    let search_range: u16 = (1 << entry_selector) * 16;
+
+    let mut font = Vec::new();
+    font.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+    font.extend_from_slice(&num_tables.to_be_bytes());
+    font.extend_from_slice(&search_range.to_be_bytes());
+    font.extend_from_slice(&entry_selector.to_be_bytes());
+    font.extend_from_slice(&(num_tables * 16 - search_range).to_be_bytes());
+
+    let mut offset = 12 + tables.len() * 16;
+    for (tag, data) in tables {
+        font.extend_from_slice(*tag);
+        font.extend_from_slice(&compute_checksum(data).to_be_bytes());
+        font.extend_from_slice(&(offset as u32).to_be_bytes());
+        font.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        offset += (data.len() + 3) & !3;
+    }
+    for (_, data) in tables {
+        font.extend_from_slice(data);
+        font.resize((font.len() + 3) & !3, 0);
+    }
+    font
+}
+
+// Returns None if the font can't be subset.
+pub fn subset_font(font: &[u8], glyph_ids: &BTreeSet<u16>) -> Option<Vec<u8>> {
+    if read_u32(font, 0)? != 0x0001_0000 && font.get(0..4)? != b"true" {
+        return None;
+    }
+    let head = find_table(font, b"head")?;
+    let glyf = find_table(font, b"glyf")?;
+    let num_glyphs = read_u16(find_table(font, b"maxp")?, 4)? as usize;
+    let is_long = read_u16(head, 50)? == 1;
+    let offsets = read_loca(find_table(font, b"loca")?, num_glyphs, is_long)?;
+    let get_glyph = |id: usize| glyf.get(offsets[id]..offsets[id + 1]);
+
+    // The glyph 0 is the .notdef glyph that must be always included.
+    let mut kept = BTreeSet::from([0]);
+    let mut stack: Vec<u16> = glyph_ids.iter().copied().collect();
+    while let Some(id) = stack.pop() {
+        if (id as usize) < num_glyphs && kept.insert(id) {
+            stack.extend(read_components(get_glyph(id as usize)?)?);
+        }
+    }
+
+    let mut new_glyf = Vec::new();
+    let mut new_loca = Vec::new();
+    for id in 0..num_glyphs {
+        new_loca.extend_from_slice(&(new_glyf.len() as u32).to_be_bytes());
+        if kept.contains(&(id as u16)) {
+            new_glyf.extend_from_slice(get_glyph(id)?);
+            new_glyf.resize((new_glyf.len() + 3) & !3, 0);
+        }
+    }
+    new_loca.extend_from_slice(&(new_glyf.len() as u32).to_be_bytes());
+
+    let mut new_head = head.to_vec();
+    new_head[8..12].copy_from_slice(&[0; 4]);
+    new_head[50..52].copy_from_slice(&1u16.to_be_bytes());
+
+    let tables: Vec<(&[u8; 4], Vec<u8>)> = KEPT_TABLES
+        .iter()
+        .filter_map(|tag| {
+            let data = match *tag {
+                b"glyf" => new_glyf.clone(),
+                b"loca" => new_loca.clone(),
+                b"head" => new_head.clone(),
+                _ => find_table(font, tag)?.to_vec(),
+            };
+            Some((*tag, data))
+        })
+        .collect();
+
+    let mut subset = build_font(&tables);
+    let adjustment = 0xB1B0_AFBAu32.wrapping_sub(compute_checksum(&subset));
+    let head_offset = (0..tables.len())
+        .find(|index| tables[*index].0 == b"head")
+        .and_then(|index| read_u32(&subset, 12 + index * 16 + 8))? as usize;
+    subset[head_offset + 8..head_offset + 12].copy_from_slice(&adjustment.to_be_bytes());
+    Some(subset)
+}
+
+// Returns six uppercase letters that tag the name of the subset.
+// The tag is made from the glyph ids with FNV-1a, so the same subset gets the same tag.
+// See 9.6.4 Font Subsets of PDF 32000-1:2008.
+pub fn to_subset_tag(glyph_ids: &BTreeSet<u16>) -> String {
+    let mut hash = 0xCBF2_9CE4_8422_2325u64;
+    for byte in glyph_ids.iter().flat_map(|id| id.to_be_bytes()) {
+        hash = (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01B3);
+    }
+    (0..6)
+        .map(|_| {
+            let letter = char::from(b'A' + (hash % 26) as u8);
+            hash /= 26;
+            letter
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The glyph 1 is a composite glyph made of the glyph 2.
+    fn build_test_font() -> Vec<u8> {
+        let simple = |byte: u8| {
+            let mut glyph = vec![0, 1];
+            glyph.resize(12, byte);
+            glyph
+        };
+        let mut composite = vec![0xFF, 0xFF, 0, 0, 0, 0, 0, 0, 0, 0];
+        composite.extend_from_slice(&[0, 0, 0, 2, 0, 0]);
+
+        let glyphs = [simple(1), composite, simple(2), simple(3)];
+        let mut glyf = Vec::new();
+        let mut loca = Vec::new();
+        for glyph in &glyphs {
+            loca.extend_from_slice(&((glyf.len() / 2) as u16).to_be_bytes());
+            glyf.extend_from_slice(glyph);
+        }
+        loca.extend_from_slice(&((glyf.len() / 2) as u16).to_be_bytes());
+
+        let mut head = vec![0; 54];
+        head[50..52].copy_from_slice(&0u16.to_be_bytes());
+        let mut maxp = vec![0, 0, 0x50, 0];
+        maxp.extend_from_slice(&(glyphs.len() as u16).to_be_bytes());
+
+        build_font(&[
+            (b"GSUB", vec![1; 8]),
+            (b"glyf", glyf),
+            (b"head", head),
+            (b"loca", loca),
+            (b"maxp", maxp),
+        ])
+    }
+
+    fn read_glyph(font: &[u8], id: usize) -> Vec<u8> {
+        let head = find_table(font, b"head").unwrap();
+        let is_long = read_u16(head, 50).unwrap() == 1;
+        let offsets = read_loca(find_table(font, b"loca").unwrap(), 4, is_long).unwrap();
+        find_table(font, b"glyf").unwrap()[offsets[id]..offsets[id + 1]].to_vec()
+    }
+
+    #[test]
+    fn it_empties_unused_glyphs() {
+        let font = build_test_font();
+        let subset = subset_font(&font, &BTreeSet::from([3])).unwrap();
+        assert_eq!(read_glyph(&subset, 0), read_glyph(&font, 0));
+        assert!(read_glyph(&subset, 1).is_empty());
+        assert!(read_glyph(&subset, 2).is_empty());
+        assert_eq!(read_glyph(&subset, 3), read_glyph(&font, 3));
+    }
+
+    #[test]
+    fn it_keeps_components_of_composite_glyphs() {
+        let font = build_test_font();
+        let subset = subset_font(&font, &BTreeSet::from([1])).unwrap();
+        assert_eq!(read_glyph(&subset, 1), read_glyph(&font, 1));
+        assert_eq!(read_glyph(&subset, 2), read_glyph(&font, 2));
+        assert!(read_glyph(&subset, 3).is_empty());
+    }
+
+    #[test]
+    fn it_drops_unused_tables() {
+        let subset = subset_font(&build_test_font(), &BTreeSet::new()).unwrap();
+        assert!(find_table(&subset, b"GSUB").is_none());
+        assert!(find_table(&subset, b"maxp").is_some());
+    }
+
+    #[test]
+    fn it_ignores_cff_fonts() {
+        let mut font = build_test_font();
+        font[0..4].copy_from_slice(b"OTTO");
+        assert_eq!(subset_font(&font, &BTreeSet::new()), None);
+    }
+
+    #[test]
+    fn it_ignores_glyph_ids_out_of_range() {
+        let subset = subset_font(&build_test_font(), &BTreeSet::from([100])).unwrap();
+        assert!(read_glyph(&subset, 3).is_empty());
+    }
+
+    #[test]
+    fn it_tags_subsets_with_six_uppercase_letters() {
+        let tag = to_subset_tag(&BTreeSet::from([1, 2, 3]));
+        assert_eq!(tag.len(), 6);
+        assert!(tag.chars().all(|c| c.is_ascii_uppercase()));
+        assert_eq!(tag, to_subset_tag(&BTreeSet::from([3, 2, 1])));
+        assert_ne!(tag, to_subset_tag(&BTreeSet::from([1, 2])));
+    }
+}
@@ -0,0 +1,574 @@
+use printpdf::{Color, LineCapStyle, LineJoinStyle, PdfLayerReference, Rgb};
+use usvg::{NodeExt, NodeKind, Paint, Stop, Transform, Units, Visibility};
+
+use crate::{
+    path::{clip_operations, paint_operation, FillRule, Path, Stroke},
+    rect::Rect,
+};
+
+// Gradients are approximated by filling bands of solid colors
+// because printpdf doesn't support shading patterns.
+const GRADIENT_BANDS: usize = 64;
+
+fn to_color(color: &usvg::Color) -> Color {
+    Color::Rgb(Rgb::new(
+        f64::from(color.red) / 255.0,
+        f64::from(color.green) / 255.0,
+        f64::from(color.blue) / 255.0,
+        None,
+    ))
+}
+
+fn get_scale(transform: &Transform) -> f64 {
+    (transform.a * transform.d - transform.b * transform.c)
+        .abs()
+        .sqrt()
+}
+
+fn invert(transform: &Transform) -> Option<Transform> {
+    let Transform { a, b, c, d, e, f } = *transform;
+    let det = a * d - b * c;
+    if det.abs() < f64::EPSILON {
+        return None;
+    }
+    Some(Transform::new(
+        d / det,
+        -b / det,
+        -c / det,
+        a / det,
+        (c * f - d * e) / det,
+        (b * e - a * f) / det,
+    ))
+}
+
+// Printpdf can't add graphics states with alpha to pages, so opacity is ignored
+// except that fully transparent groups aren't drawn.
+fn is_transparent(node: &usvg::Node) -> bool {
+    node.ancestors().any(|node| match *node.borrow() {
+        NodeKind::Group(ref group) => group.opacity.value() <= 0.0,
+        _ => false,
+    })
+}
+
+fn to_path(data: &usvg::PathData, transform: &Transform) -> Path {
+    let mut path = Path::new();
+    for segment in data.0.iter() {
+        match *segment {
+            usvg::PathSegment::MoveTo { x, y } => {
+                let (x, y) = transform.apply(x, y);
+                path.move_to(x, y);
+            }
+            usvg::PathSegment::LineTo { x, y } => {
+                let (x, y) = transform.apply(x, y);
+                path.line_to(x, y);
+            }
+            usvg::PathSegment::CurveTo {
+                x1,
+                y1,
+                x2,
+                y2,
+                x,
+                y,
+            } => {
+                let (x1, y1) = transform.apply(x1, y1);
+                let (x2, y2) = transform.apply(x2, y2);
+                let (x, y) = transform.apply(x, y);
+                path.curve_to(x1, y1, x2, y2, x, y);
+            }
+            usvg::PathSegment::ClosePath => path.close(),
+        }
+    }
+    path
+}
+
+// Linear interpolation of the stop colors, the offset is clamped
+// same as `spreadMethod="pad"`.
+fn get_stop_color(stops: &[Stop], offset: f64) -> Color {
+    let offset = offset.clamp(0.0, 1.0);
+    let index = stops
+        .iter()
+        .position(|stop| offset <= stop.offset.value())
+        .unwrap_or(stops.len() - 1);
+    if index == 0 || offset >= stops[index].offset.value() {
+        return to_color(&stops[index].color);
+    }
+    let (start, end) = (&stops[index - 1], &stops[index]);
+    let ratio = (offset - start.offset.value()) / (end.offset.value() - start.offset.value());
+    let mix =
+        |from: u8, to: u8| (f64::from(from) + (f64::from(to) - f64::from(from)) * ratio) / 255.0;
+    Color::Rgb(Rgb::new(
+        mix(start.color.red, end.color.red),
+        mix(start.color.green, end.color.green),
+        mix(start.color.blue, end.color.blue),
+        None,
+    ))
+}
+
+#[derive(Debug, Clone)]
+pub enum SvgPaint {
+    Color(Color),
+    // Bands are drawn in order inside the clipped shape.
+    Bands(Vec<(Path, Color)>),
+}
+
+#[derive(Debug, Clone)]
+pub struct SvgFill {
+    pub paint: SvgPaint,
+    pub rule: FillRule,
+}
+
+// Paths are kept in pixels of the SVG from the top-left,
+// they are mapped to the image rect when drawing.
+#[derive(Debug, Clone)]
+pub struct SvgShape {
+    pub path: Path,
+    pub fill: Option<SvgFill>,
+    pub stroke: Option<Stroke>,
+}
+
+impl SvgShape {
+    fn new(tree: &usvg::Tree, path: &usvg::Path, transform: &Transform) -> Self {
+        let fill = path
+            .fill
+            .as_ref()
+            .filter(|fill| fill.opacity.value() > 0.0)
+            .map(|fill| SvgFill {
+                paint: Self::build_paint(tree, path, &fill.paint, transform),
+                rule: match fill.rule {
+                    usvg::FillRule::NonZero => FillRule::NonZero,
+                    usvg::FillRule::EvenOdd => FillRule::EvenOdd,
+                },
+            });
+        let scale = get_scale(transform);
+        let stroke = path
+            .stroke
+            .as_ref()
+            .filter(|stroke| stroke.opacity.value() > 0.0)
+            .map(|stroke| Stroke {
+                color: Self::build_solid_color(tree, &stroke.paint),
+                width: stroke.width.value() * scale,
+                cap: match stroke.linecap {
+                    usvg::LineCap::Butt => LineCapStyle::Butt,
+                    usvg::LineCap::Round => LineCapStyle::Round,
+                    usvg::LineCap::Square => LineCapStyle::ProjectingSquare,
+                },
+                join: match stroke.linejoin {
+                    usvg::LineJoin::Miter => LineJoinStyle::Miter,
+                    usvg::LineJoin::Round => LineJoinStyle::Round,
+                    usvg::LineJoin::Bevel => LineJoinStyle::Limit,
+                },
+                dash: stroke.dasharray.as_ref().map(|dasharray| {
+                    (
+                        dasharray.iter().map(|dash| dash * scale).collect(),
+                        f64::from(stroke.dashoffset) * scale,
+                    )
+                }),
+            });
+        Self {
+            path: to_path(&path.data, transform),
+            fill,
+            stroke,
+        }
+    }
+
+    // Strokes with gradients are drawn with the first stop color.
+    fn build_solid_color(tree: &usvg::Tree, paint: &Paint) -> Color {
+        let black = Color::Rgb(Rgb::new(0.0, 0.0, 0.0, None));
+        match paint {
+            Paint::Color(color) => to_color(color),
+            Paint::Link(id) => tree
+                .defs_by_id(id)
+                .and_then(|node| {
+                    let kind = node.borrow();
+                    match *kind {
+                        NodeKind::LinearGradient(ref gradient) => {
+                            gradient.stops.first().map(|stop| to_color(&stop.color))
+                        }
+                        NodeKind::RadialGradient(ref gradient) => {
+                            gradient.stops.first().map(|stop| to_color(&stop.color))
+                        }
+                        _ => None,
+                    }
+                })
+                .unwrap_or(black),
+        }
+    }
+
+    fn build_paint(
+        tree: &usvg::Tree,
+        path: &usvg::Path,
+        paint: &Paint,
+        transform: &Transform,
+    ) -> SvgPaint {
+        let id = match paint {
+            Paint::Color(color) => return SvgPaint::Color(to_color(color)),
+            Paint::Link(id) => id,
+        };
+        let bbox = path.data.bbox();
+        let bands = tree.defs_by_id(id).and_then(|node| {
+            let kind = node.borrow();
+            match *kind {
+                NodeKind::LinearGradient(ref gradient) => {
+                    let gradient_transform = Self::get_gradient_transform(&gradient.base, bbox)?;
+                    Self::build_linear_bands(gradient, &gradient_transform, bbox?)
+                }
+                NodeKind::RadialGradient(ref gradient) => {
+                    let gradient_transform = Self::get_gradient_transform(&gradient.base, bbox)?;
+                    Self::build_radial_bands(gradient, &gradient_transform, bbox?)
+                }
+                _ => None,
+            }
+        });
+        match bands {
+            Some(bands) => SvgPaint::Bands(
+                bands
+                    .into_iter()
+                    .map(|(band, color)| (band.map_points(|x, y| transform.apply(x, y)), color))
+                    .collect(),
+            ),
+            None => SvgPaint::Color(Self::build_solid_color(tree, paint)),
+        }
+    }
+
+    // Returns the transform from the gradient space to the user space.
+    fn get_gradient_transform(
+        base: &usvg::BaseGradient,
+        bbox: Option<usvg::PathBbox>,
+    ) -> Option<Transform> {
+        let mut transform = Transform::default();
+        if base.units == Units::ObjectBoundingBox {
+            let bbox = bbox?;
+            transform.append(&Transform::new(
+                bbox.width(),
+                0.0,
+                0.0,
+                bbox.height(),
+                bbox.x(),
+                bbox.y(),
+            ));
+        }
+        transform.append(&base.transform);
+        Some(transform)
+    }
+
+    fn build_linear_bands(
+        gradient: &usvg::LinearGradient,
+        transform: &Transform,
+        bbox: usvg::PathBbox,
+    ) -> Option<Vec<(Path, Color)>> {
+        let (dx, dy) = (gradient.x2 - gradient.x1, gradient.y2 - gradient.y1);
+        let length = (dx * dx + dy * dy).sqrt();
+        if gradient.stops.is_empty() || length < f64::EPSILON {
+            return None;
+        }
+        let (ux, uy) = (dx / length, dy / length);
+
+        // Find the range of the shape along the gradient vector
+        // so that the bands cover the whole shape.
+        let inverted = invert(transform)?;
+        let corners = [
+            (bbox.x(), bbox.y()),
+            (bbox.right(), bbox.y()),
+            (bbox.right(), bbox.bottom()),
+            (bbox.x(), bbox.bottom()),
+        ];
+        let (mut t_min, mut t_max, mut s_min, mut s_max) = (0.0_f64, 1.0_f64, 0.0_f64, 0.0_f64);
+        for (x, y) in corners {
+            let (x, y) = inverted.apply(x, y);
+            let (rx, ry) = (x - gradient.x1, y - gradient.y1);
+            let t = (rx * ux + ry * uy) / length;
+            let s = rx * -uy + ry * ux;
+            t_min = t_min.min(t);
+            t_max = t_max.max(t);
+            s_min = s_min.min(s);
+            s_max = s_max.max(s);
+        }
+
+        let point = |t: f64, s: f64| {
+            transform.apply(gradient.x1 + dx * t - uy * s, gradient.y1 + dy * t + ux * s)
+        };
+        let step = 1.0 / GRADIENT_BANDS as f64;
+        let mut bands = vec![];
+        let mut t = t_min;
+        while t < t_max {
+            // Outside of the gradient vector is filled with a single band.
+            let next = if t < 0.0 {
+                0.0
+            } else if t >= 1.0 {
+                t_max
+            } else {
+                (t + step).min(t_max)
+            };
+            let band = Path::from_polygon(
+                &[
+                    point(t, s_min),
+                    point(next, s_min),
+                    point(next, s_max),
+                    point(t, s_max),
+                ],
+                true,
+            );
+            bands.push((band, get_stop_color(&gradient.stops, (t + next) / 2.0)));
+            t = next;
+        }
+        Some(bands)
+    }
+
+    // The focal point is ignored and the bands are drawn
+    // as concentric circles from the outside.
+    fn build_radial_bands(
+        gradient: &usvg::RadialGradient,
+        transform: &Transform,
+        bbox: usvg::PathBbox,
+    ) -> Option<Vec<(Path, Color)>> {
+        let radius = gradient.r.value();
+        let last = gradient.stops.last()?;
+        let mut bands = vec![(
+            Path::from_polygon(
+                &[
+                    (bbox.x(), bbox.y()),
+                    (bbox.right(), bbox.y()),
+                    (bbox.right(), bbox.bottom()),
+                    (bbox.x(), bbox.bottom()),
+                ],
+                true,
+            ),
+            to_color(&last.color),
+        )];
+        for index in (1..=GRADIENT_BANDS).rev() {
+            let ratio = index as f64 / GRADIENT_BANDS as f64;
+            let circle =
+                Path::from_ellipse(gradient.cx, gradient.cy, radius * ratio, radius * ratio)
+                    .map_points(|x, y| transform.apply(x, y));
+            let offset = (index as f64 - 0.5) / GRADIENT_BANDS as f64;
+            bands.push((circle, get_stop_color(&gradient.stops, offset)));
+        }
+        Some(bands)
+    }
+
+    fn map_points<F: Fn(f64, f64) -> (f64, f64)>(&self, map: F) -> Self {
+        let fill = self.fill.as_ref().map(|fill| SvgFill {
+            paint: match &fill.paint {
+                SvgPaint::Color(color) => SvgPaint::Color(color.clone()),
+                SvgPaint::Bands(bands) => SvgPaint::Bands(
+                    bands
+                        .iter()
+                        .map(|(band, color)| (band.map_points(&map), color.clone()))
+                        .collect(),
+                ),
+            },
+            rule: fill.rule,
+        });
+        Self {
+            path: self.path.map_points(&map),
+            fill,
+            stroke: self.stroke.clone(),
+        }
+    }
+
+    fn draw(&self, pdf_layer: &PdfLayerReference, scale: f64) {
+        let path = &self.path;
+        pdf_layer.save_graphics_state();
+        if let Some(stroke) = &self.stroke {
+            stroke.apply(pdf_layer, scale);
+        }
+        match &self.fill {
+            Some(SvgFill {
+                paint: SvgPaint::Color(color),
+                rule,
+            }) => {
+                pdf_layer.set_fill_color(color.clone());
+                path.add_to_layer(pdf_layer);
+                pdf_layer.add_operation(paint_operation(Some(*rule), self.stroke.is_some()));
+            }
+            Some(SvgFill {
+                paint: SvgPaint::Bands(bands),
+                rule,
+            }) => {
+                pdf_layer.save_graphics_state();
+                path.add_to_layer(pdf_layer);
+                for operation in clip_operations(*rule) {
+                    pdf_layer.add_operation(operation);
+                }
+                for (band, color) in bands {
+                    pdf_layer.set_fill_color(color.clone());
+                    band.add_to_layer(pdf_layer);
+                    pdf_layer.add_operation(paint_operation(Some(FillRule::NonZero), false));
+                }
+                pdf_layer.restore_graphics_state();
+                if self.stroke.is_some() {
+                    path.add_to_layer(pdf_layer);
+                    pdf_layer.add_operation(paint_operation(None, true));
+                }
+            }
+            None => {
+                path.add_to_layer(pdf_layer);
+                pdf_layer.add_operation(paint_operation(None, self.stroke.is_some()));
+            }
+        }
+        pdf_layer.restore_graphics_state();
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SvgImage {
+    pub width: f64,
+    pub height: f64,
+    pub shapes: Vec<SvgShape>,
+}
+
+impl SvgImage {
+    // Only paths are supported, so images and texts in the SVG are ignored.
+    // Texts should be converted to paths beforehand.
+    // Clip paths and masks are ignored, and shapes with opacity are drawn opaque.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, usvg::Error> {
+        let tree = usvg::Tree::from_data(bytes, &usvg::Options::default().to_ref())?;
+        let (size, view_box) = {
+            let svg = tree.svg_node();
+            (svg.size, svg.view_box)
+        };
+        let view_box_transform =
+            usvg::utils::view_box_to_transform(view_box.rect, view_box.aspect, size);
+
+        let shapes = tree
+            .root()
+            .descendants()
+            .filter(|node| !tree.is_in_defs(node))
+            .filter_map(|node| {
+                let kind = node.borrow();
+                match *kind {
+                    NodeKind::Path(ref path)
+                        if path.visibility == Visibility::Visible && !is_transparent(&node) =>
+                    {
+                        let mut transform = view_box_transform;
+                        transform.append(&node.abs_transform());
+                        Some(SvgShape::new(&tree, path, &transform))
+                    }
+                    _ => None,
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            width: size.width(),
+            height: size.height(),
+            shapes,
+        })
+    }
+
+    // The rect is based on the bottom-left same as printpdf.
+    pub fn draw(&self, pdf_layer: &PdfLayerReference, rect: &Rect) {
+        let scale_x = rect.width / self.width;
+        let scale_y = rect.height / self.height;
+        let map = |x: f64, y: f64| (rect.x + x * scale_x, rect.y + rect.height - y * scale_y);
+        for shape in &self.shapes {
+            shape
+                .map_points(map)
+                .draw(pdf_layer, (scale_x + scale_y) / 2.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lopdf::content::Content;
+    use printpdf::{Mm, PdfDocument, Pt};
+
+    use super::*;
+    use crate::path::PathSegment;
+
+    fn parse(body: &str) -> SvgImage {
+        let svg = format!(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="20" viewBox="0 0 5 10">{}</svg>"##,
+            body
+        );
+        SvgImage::from_bytes(svg.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn it_parses_paths_in_pixels_from_the_top_left() {
+        let svg = parse(r##"<rect x="1" y="2" width="3" height="4" />"##);
+        assert_eq!((svg.width, svg.height), (10.0, 20.0));
+        assert_eq!(svg.shapes.len(), 1);
+        let segments = &svg.shapes[0].path.segments;
+        assert_eq!(segments[0], PathSegment::MoveTo(2.0, 4.0));
+        assert!(segments.contains(&PathSegment::LineTo(8.0, 12.0)));
+        assert_eq!(segments.last(), Some(&PathSegment::ClosePath));
+    }
+
+    #[test]
+    fn it_parses_fills_and_strokes() {
+        let svg = parse(
+            r##"<path d="M0 0 L5 10" fill="#ff0000" fill-rule="evenodd" stroke="#0000ff" stroke-width="2" stroke-dasharray="1 2" />"##,
+        );
+        let shape = &svg.shapes[0];
+        let fill = shape.fill.as_ref().unwrap();
+        assert_eq!(fill.rule, FillRule::EvenOdd);
+        assert!(matches!(
+            fill.paint,
+            SvgPaint::Color(Color::Rgb(Rgb { r, g, b, .. })) if (r, g, b) == (1.0, 0.0, 0.0)
+        ));
+        let stroke = shape.stroke.as_ref().unwrap();
+        assert_eq!(stroke.width, 4.0);
+        assert_eq!(stroke.dash, Some((vec![2.0, 4.0], 0.0)));
+    }
+
+    #[test]
+    fn it_parses_gradients_as_bands() {
+        let svg = parse(
+            r##"
+                <linearGradient id="gradient"><stop offset="0" stop-color="#000" /><stop offset="1" stop-color="#fff" /></linearGradient>
+                <rect width="5" height="10" fill="url(#gradient)" />
+            "##,
+        );
+        match &svg.shapes[0].fill.as_ref().unwrap().paint {
+            SvgPaint::Bands(bands) => assert!(bands.len() > 1),
+            paint => panic!("unexpected paint: {:?}", paint),
+        }
+    }
+
+    #[test]
+    fn it_ignores_hidden_and_transparent_shapes() {
+        let svg = parse(
+            r##"
+                <rect width="1" height="1" visibility="hidden" />
+                <g opacity="0"><rect width="1" height="1" /></g>
+                <rect width="1" height="1" fill-opacity="0" stroke="#000" stroke-opacity="0" />
+            "##,
+        );
+        assert_eq!(svg.shapes.len(), 1);
+        assert!(svg.shapes[0].fill.is_none());
+        assert!(svg.shapes[0].stroke.is_none());
+    }
+
+    #[test]
+    fn it_draws_shapes_in_the_rect_from_the_bottom_left() {
+        let svg = parse(r##"<rect x="1" y="2" width="3" height="4" stroke="#000" />"##);
+        let (pdf_doc, page_index, layer_index) = PdfDocument::new("", Mm(10.0), Mm(20.0), "");
+        let pdf_layer = pdf_doc.get_page(page_index).get_layer(layer_index);
+        svg.draw(&pdf_layer, &Rect::new(0.0, 0.0, 10.0, 20.0));
+
+        let doc = lopdf::Document::load_mem(&pdf_doc.save_to_bytes().unwrap()).unwrap();
+        let page_id = doc.get_pages()[&1];
+        let content = Content::decode(&doc.get_page_content(page_id).unwrap()).unwrap();
+        let operators: Vec<&str> = content
+            .operations
+            .iter()
+            .map(|operation| operation.operator.as_str())
+            .collect();
+        assert!(operators.contains(&"w"));
+        assert!(operators.contains(&"B"));
+        let move_to = content
+            .operations
+            .iter()
+            .find(|operation| operation.operator == "m")
+            .unwrap();
+        let point: Vec<f64> = move_to
+            .operands
+            .iter()
+            .map(|operand| operand.as_f64().unwrap())
+            .collect();
+        assert!((point[0] - Pt::from(Mm(2.0)).0).abs() < 0.01);
+        assert!((point[1] - Pt::from(Mm(16.0)).0).abs() < 0.01);
+    }
+}
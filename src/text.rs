@@ -1,70 +1,694 @@
-use printpdf::{Mm, PdfPageReference};
-use rusttype::{Font, Scale};
+use std::ops::Range;
+
+use lopdf::content::Operation;
+use printpdf::{Mm, PdfLayerReference, PdfPageReference, Pt};
+use rustybuzz::Feature;
+use unicode_bidi::{BidiInfo, Level};
+use unicode_vo::{char_orientation, Orientation};
 
 use crate::{
     assets::{Assets, FontAssetItem},
     border::Border,
-    dom::{validate_name, Children},
+    clip::Clip,
+    dom::{get_attr, validate_name, Children, Element, InlineChild},
     error::Error,
     hex_color::HexColor,
+    hyphenation::{find_hyphenation_points, Language},
     layout::{LayoutNode, LayoutTree},
+    line_breaking::{find_break_opportunities, find_optimal_breaks, Item, INFINITE_PENALTY},
     rect::Rect,
+    shaping::{build_text_operations, parse_features, reorder_line, split_clusters, Glyph},
+    style::{
+        Direction, FontSizeFit, Hyphens, Overflow, Style, TabAlign, TabStop, TextAlign,
+        TextOverflow, WhiteSpace, WritingMode,
+    },
 };
 
+const ELLIPSIS: &str = "\u{2026}";
+const HYPHEN: &str = "-";
+// Same as `\hyphenpenalty` of TeX.
+const HYPHEN_PENALTY: f64 = 50.0;
+// Font sizes are fitted within this many points, and it is also the smallest size.
+const FONT_SIZE_PRECISION: f64 = 0.1;
+
+// Returns the width of the text in millimeters.
+pub fn get_text_width(
+    font: &FontAssetItem,
+    text: &str,
+    font_size: f64,
+    features: &[Feature],
+) -> f64 {
+    let multi_line = MultiLine {
+        text,
+        fonts: vec![font],
+        features,
+        max_width: f64::MAX,
+        font_size,
+        bidi_info: BidiInfo::new(text, None),
+        no_breaks: vec![],
+        hyphens: vec![],
+        break_opportunities: vec![],
+    };
+    multi_line.compute_char_widths().iter().sum()
+}
+
+// Same as `use_text` of printpdf, but the text is shaped and reordered.
+// The direction is detected from the first strong character.
+pub fn use_text(
+    pdf_layer: &PdfLayerReference,
+    font: &FontAssetItem,
+    text: &str,
+    font_size: f64,
+    features: &[Feature],
+    (x, y): (Mm, Mm),
+) -> Result<(), Error> {
+    let bidi_info = BidiInfo::new(text, None);
+    pdf_layer.begin_text_section();
+    pdf_layer.set_text_cursor(x, y);
+    for (run, is_rtl) in reorder_line(&bidi_info, 0..text.len()) {
+        let run = &text[run];
+        let glyphs = font.shape(run, features, is_rtl);
+        pdf_layer.set_font(font.embed(&glyphs, run)?, font_size);
+        write_operations(pdf_layer, build_text_operations(&glyphs, font_size));
+    }
+    pdf_layer.end_text_section();
+    Ok(())
+}
+
+fn write_operations(pdf_layer: &PdfLayerReference, operations: Vec<Operation>) {
+    for operation in operations {
+        pdf_layer.add_operation(operation);
+    }
+}
+
+// Moves the start of the text to the position in millimeters.
+// Glyphs are rotated 90 degrees clockwise if `is_rotated`.
+fn set_text_matrix(pdf_layer: &PdfLayerReference, x: f64, y: f64, is_rotated: bool) {
+    let matrix = if is_rotated {
+        [0.0, -1.0, 1.0, 0.0]
+    } else {
+        [1.0, 0.0, 0.0, 1.0]
+    };
+    let mut operands: Vec<lopdf::Object> = matrix.iter().map(|value| (*value).into()).collect();
+    operands.push(Pt::from(Mm(x)).0.into());
+    operands.push(Pt::from(Mm(y)).0.into());
+    pdf_layer.add_operation(Operation::new("Tm", operands));
+}
+
+// CJK characters are upright in vertical lines, and the others such as Latin are rotated.
+// Characters like brackets are also upright because they are replaced by vertical alternates.
+fn is_upright(c: char) -> bool {
+    !matches!(char_orientation(c), Orientation::Rotated)
+}
+
+// `line_height` is scaled by the ratio of the fitted size to `font_size`,
+// and it's kept as it is if `font_size` isn't positive.
+fn scale_line_height(style: &Style, font_size: f64) -> f64 {
+    if style.font_size > 0.0 {
+        style.line_height * font_size / style.font_size
+    } else {
+        style.line_height
+    }
+}
+
+// Bounds are clamped so that sizes are positive and the minimum isn't above the maximum.
+fn clamp_font_size_fit(fit: FontSizeFit) -> (f64, f64) {
+    let min = fit.min.max(FONT_SIZE_PRECISION);
+    (min, fit.max.max(min))
+}
+
+// Tabs aren't stretched because they advance to tab stops.
+fn is_stretchable(c: char) -> bool {
+    c.is_whitespace() && c != '\t'
+}
+
+// Widens advances of spaces by the size in em to justify the line.
+fn stretch_spaces(glyphs: &mut [Glyph], text: &str, size: f64) {
+    for glyph in glyphs {
+        if text[glyph.cluster..].starts_with(is_stretchable) {
+            glyph.x_advance += size;
+        }
+    }
+}
+
+fn count_spaces(text: &str) -> usize {
+    text.chars().filter(|c| is_stretchable(*c)).count()
+}
+
+// Tabs are shaped as spaces because fonts usually don't have glyphs of them.
+fn replace_tabs(text: &str) -> String {
+    text.replace('\t', " ")
+}
+
+// Splits the text into runs of characters that have the same key.
+fn split_text_by<T: Copy + PartialEq>(text: &str, key: impl Fn(char) -> T) -> Vec<(T, &str)> {
+    let mut runs = Vec::new();
+    let mut from = 0;
+    let mut current = None;
+    for (index, c) in text.char_indices() {
+        let value = key(c);
+        if let Some(current) = current.filter(|current| *current != value) {
+            runs.push((current, &text[from..index]));
+            from = index;
+        }
+        current = Some(value);
+    }
+    if let Some(current) = current {
+        runs.push((current, &text[from..]));
+    }
+    runs
+}
+
+// A ruby annotation over the range of bytes of the base text.
+struct Ruby {
+    base: Range<usize>,
+    text: String,
+}
+
+// Ruby is given by `<Ruby base="..." text="..." />`
+// or by `<Ruby><Rb>...</Rb><Rt>...</Rt></Ruby>`.
+fn parse_ruby(element: &Element) -> Result<(String, String), Error> {
+    let mut base: Option<String> = get_attr(element, "base");
+    let mut text: Option<String> = get_attr(element, "text");
+    if let Children::Elements(children) = &element.children {
+        for child in children {
+            let value = match &child.children {
+                Children::Text(value) => Some(value.trim().to_string()),
+                _ => None,
+            };
+            match child.name.as_str() {
+                "Rb" => base = value,
+                "Rt" => text = value,
+                _ => {
+                    return Err(Error::NotSupportElement(
+                        child.name.to_string(),
+                        element.name.to_string(),
+                    ))
+                }
+            }
+        }
+    }
+    let required = |key: &str| Error::RequiredAttribute(key.to_string(), element.name.to_string());
+    Ok((
+        base.ok_or_else(|| required("base"))?,
+        text.ok_or_else(|| required("text"))?,
+    ))
+}
+
+// Whitespaces including newlines are collapsed into a space in `Normal`,
+// and they are kept in the other modes.
+fn push_text(text: &mut String, child: &str, white_space: WhiteSpace) {
+    if white_space != WhiteSpace::Normal {
+        let child = child.replace("\r\n", "\n");
+        // A newline just after the start tag is ignored same as `<pre>` of HTML.
+        let child = match child.strip_prefix('\n') {
+            Some(child) if text.is_empty() => child,
+            _ => &child,
+        };
+        text.push_str(child);
+        return;
+    }
+    for c in child.chars() {
+        if !c.is_whitespace() {
+            text.push(c);
+        } else if !text.is_empty() && !text.ends_with([' ', '\n']) {
+            text.push(' ');
+        }
+    }
+}
+
+// Removes spaces at the end of the text in `Normal`,
+// or the indentation of the end tag in the other modes.
+fn trim_text_end(text: &mut String, white_space: WhiteSpace) {
+    let end = text.trim_end_matches([' ', '\t']).len();
+    if white_space == WhiteSpace::Normal {
+        text.truncate(end);
+    } else if text[..end].ends_with('\n') {
+        text.truncate(end - 1);
+    }
+}
+
+// Joins texts of children, and inline elements are replaced by their base texts.
+// `<Br />` is replaced by a newline that is a hard break.
+fn flatten_children(
+    children: &Children,
+    white_space: WhiteSpace,
+) -> Result<(String, Vec<Ruby>), Error> {
+    let mut text = String::new();
+    let mut rubies = Vec::new();
+    let mut push_element = |element: &Element, text: &mut String| -> Result<(), Error> {
+        if element.name == "Br" {
+            if white_space == WhiteSpace::Normal {
+                trim_text_end(text, white_space);
+            }
+            text.push('\n');
+            return Ok(());
+        }
+        validate_name(element, "Ruby")?;
+        let (base, ruby) = parse_ruby(element)?;
+        let start = text.len();
+        text.push_str(&base);
+        rubies.push(Ruby {
+            base: start..text.len(),
+            text: ruby,
+        });
+        Ok(())
+    };
+    match children {
+        Children::Text(child) => push_text(&mut text, child, white_space),
+        Children::Elements(elements) => {
+            for element in elements {
+                push_element(element, &mut text)?;
+            }
+        }
+        Children::Inline(children) => {
+            for child in children {
+                match child {
+                    InlineChild::Text(child) => push_text(&mut text, child, white_space),
+                    InlineChild::Element(element) => push_element(element, &mut text)?,
+                }
+            }
+        }
+    }
+    trim_text_end(&mut text, white_space);
+    Ok((text, rubies))
+}
+
 struct MultiLine<'a> {
     text: &'a str,
-    rusttype_font: &'a Font<'a>,
+    // Fallback fonts in order of priority.
+    fonts: Vec<&'a FontAssetItem<'a>>,
+    features: &'a [Feature],
     max_width: f64,
-    font_size: f32,
+    font_size: f64,
+    bidi_info: BidiInfo<'a>,
+    // Ranges of bytes that can't be split into lines, e.g. bases of rubies.
+    no_breaks: Vec<Range<usize>>,
+    // Positions of bytes in ascending order where words can be hyphenated.
+    hyphens: Vec<usize>,
+    // Positions of bytes in ascending order where lines can start by UAX #14.
+    break_opportunities: Vec<usize>,
 }
 
-fn to_mm_size(font_size: f64) -> f64 {
+pub fn to_mm_size(font_size: f64) -> f64 {
     let pt = printpdf::Pt(font_size);
     Mm::from(pt).0
 }
 
 impl<'a> MultiLine<'a> {
-    pub fn split_text_to_multi_lines(&self) -> Vec<&str> {
+    // Lines are ranges of bytes of the text in logical order.
+    // Words wider than the line are broken between characters.
+    pub fn split_text_to_multi_lines(
+        &self,
+        widths: &[f64],
+        hyphen_width: f64,
+    ) -> Vec<Range<usize>> {
         let mut from = 0;
         let mut sum_width = 0.0;
         let mut lines = Vec::new();
-        let widths = self.compute_char_widths();
 
-        for (index, (to, _)) in self.text.char_indices().enumerate() {
+        for (index, (position, c)) in self.text.char_indices().enumerate() {
+            // Newlines are hard breaks, and they aren't written.
+            if c == '\n' {
+                lines.push(from..position);
+                sum_width = 0.0;
+                from = position + 1;
+                continue;
+            }
             if widths.len() - 1 <= index {
                 break;
             }
             if (self.max_width) <= ((sum_width + widths[index + 1]).ceil()) {
-                lines.push(&self.text[from..to]);
-                sum_width = 0.0;
-                from = to;
+                let (to, next) = self.find_break(widths, from, position, hyphen_width);
+                lines.push(from..to);
+                sum_width = self.get_line_width(widths, &(next..position));
+                from = next;
             }
             sum_width += widths[index];
         }
 
-        if from != self.text.len() - 1 {
-            lines.push(&self.text[from..]);
+        if from < self.text.len() {
+            lines.push(from..self.text.len());
         }
         lines
     }
 
+    // Lines are broken by the algorithm of Knuth and Plass at spaces, the other break
+    // opportunities of UAX #14 and hyphenation points.
+    // Spaces can be stretched by half and shrunk by a third of their widths.
+    // `None` is returned if a word is wider than the line.
+    pub fn split_text_to_optimal_lines(
+        &self,
+        widths: &[f64],
+        hyphen_width: f64,
+    ) -> Option<Vec<Range<usize>>> {
+        let mut items = Vec::new();
+        // The end of the line and the start of the next line when it's broken at each item.
+        let mut breaks = Vec::new();
+        // The last line of a paragraph can be filled by a glue, and it's broken by force.
+        let paragraph_end = [
+            Item::Penalty {
+                width: 0.0,
+                cost: INFINITE_PENALTY,
+                is_flagged: false,
+            },
+            Item::Glue {
+                width: 0.0,
+                stretch: self.max_width,
+                shrink: 0.0,
+            },
+            Item::Penalty {
+                width: 0.0,
+                cost: -INFINITE_PENALTY,
+                is_flagged: false,
+            },
+        ];
+        for ((position, c), width) in self.text.char_indices().zip(widths) {
+            if c == '\n' {
+                items.extend(paragraph_end);
+                breaks.extend([(position, position + 1); 3]);
+                continue;
+            }
+            let is_breakable = !self.is_in_no_breaks(position);
+            if c.is_whitespace() && is_breakable {
+                items.push(Item::Glue {
+                    width: *width,
+                    stretch: width / 2.0,
+                    shrink: width / 3.0,
+                });
+                breaks.push((position, position + c.len_utf8()));
+                continue;
+            }
+            let is_after_space = self.text[..position].ends_with(char::is_whitespace);
+            if is_breakable && !is_after_space && self.is_break_opportunity(position) {
+                // Lines without spaces such as CJK texts can be broken between characters,
+                // and they can end a little short because the gaps aren't drawn wider.
+                items.push(Item::Glue {
+                    width: 0.0,
+                    stretch: width / 4.0,
+                    shrink: 0.0,
+                });
+                breaks.push((position, position));
+            } else if is_breakable && self.hyphens.binary_search(&position).is_ok() {
+                items.push(Item::Penalty {
+                    width: hyphen_width,
+                    cost: HYPHEN_PENALTY,
+                    is_flagged: true,
+                });
+                breaks.push((position, position));
+            }
+            items.push(Item::Box { width: *width });
+            breaks.push((position, position));
+        }
+        items.extend(paragraph_end);
+        breaks.extend([(self.text.len(), self.text.len()); 3]);
+
+        let mut from = 0;
+        let mut lines = Vec::new();
+        for index in find_optimal_breaks(&items, self.max_width)? {
+            let (to, next) = breaks[index];
+            // The text doesn't end with an empty line same as greedy breaking.
+            if from < self.text.len() || to < self.text.len() {
+                lines.push(from..to);
+            }
+            from = next;
+        }
+        Some(lines)
+    }
+
+    // Returns the end of the line and the start of the next line.
+    // The line is broken at the last break opportunity of UAX #14 or hyphenation point that fits,
+    // and a space at the break isn't written. If there is none, the line is broken at `to`.
+    fn find_break(
+        &self,
+        widths: &[f64],
+        from: usize,
+        to: usize,
+        hyphen_width: f64,
+    ) -> (usize, usize) {
+        let mut found = None;
+        let mut prev = None;
+        for (index, c) in self.text[from..].char_indices() {
+            let position = from + index;
+            if to < position {
+                break;
+            }
+            if from < position && !self.is_in_no_breaks(position) {
+                if c.is_whitespace() {
+                    // Spaces such as no-break spaces don't allow breaks after them.
+                    if self.is_break_opportunity(position + c.len_utf8()) {
+                        found = Some((position, position + c.len_utf8()));
+                    }
+                } else if !prev.is_some_and(char::is_whitespace)
+                    && self.is_break_opportunity(position)
+                {
+                    found = Some((position, position));
+                } else if self.hyphens.binary_search(&position).is_ok() {
+                    let width = self.get_line_width(widths, &(from..position));
+                    if width + hyphen_width <= self.max_width {
+                        found = Some((position, position));
+                    }
+                }
+            }
+            prev = Some(c);
+        }
+        found.unwrap_or_else(|| {
+            let to = self.avoid_no_breaks(from, to);
+            (to, to)
+        })
+    }
+
+    fn is_break_opportunity(&self, position: usize) -> bool {
+        self.break_opportunities.binary_search(&position).is_ok()
+    }
+
+    fn is_in_no_breaks(&self, position: usize) -> bool {
+        self.no_breaks
+            .iter()
+            .any(|range| range.start < position && position < range.end)
+    }
+
+    // Moves the break before the range that can't be split,
+    // unless the range starts the line.
+    fn avoid_no_breaks(&self, from: usize, to: usize) -> usize {
+        self.no_breaks
+            .iter()
+            .find(|range| from < range.start && range.start < to && to < range.end)
+            .map_or(to, |range| range.start)
+    }
+
+    // The line is the last line of a paragraph if it's followed by a newline or nothing.
+    pub fn is_paragraph_end(&self, line: &Range<usize>) -> bool {
+        self.text[line.end..]
+            .chars()
+            .next()
+            .map_or(true, |c| c == '\n')
+    }
+
+    // The line ends with a visible hyphen if it's broken at a hyphenation point.
+    pub fn is_hyphenated(&self, line: &Range<usize>) -> bool {
+        self.hyphens.binary_search(&line.end).is_ok()
+    }
+
+    pub fn get_line_width(&self, widths: &[f64], line: &Range<usize>) -> f64 {
+        self.text
+            .char_indices()
+            .zip(widths)
+            .filter(|((index, _), _)| line.contains(index))
+            .map(|(_, width)| width)
+            .sum()
+    }
+
+    // Removes characters from the end of the line until it fits in the width,
+    // and trailing spaces are also removed so that the ellipsis follows a word.
+    pub fn truncate_line(
+        &self,
+        widths: &[f64],
+        line: &Range<usize>,
+        max_width: f64,
+    ) -> Range<usize> {
+        // The line is measured once, and widths of removed characters are subtracted.
+        let chars: Vec<((usize, char), &f64)> = self
+            .text
+            .char_indices()
+            .zip(widths)
+            .filter(|((index, _), _)| line.contains(index))
+            .collect();
+        let mut width: f64 = chars.iter().map(|(_, width)| *width).sum();
+        let mut end = line.end;
+        for ((index, c), char_width) in chars.into_iter().rev() {
+            if width <= max_width && !c.is_whitespace() {
+                break;
+            }
+            width -= char_width;
+            end = index;
+        }
+        line.start..end
+    }
+
+    // Returns the first font that has the mark such as an ellipsis and the width of it.
+    pub fn measure_mark(&self, mark: &str) -> (&'a FontAssetItem<'a>, f64) {
+        let c = mark.chars().next().unwrap_or(' ');
+        let font = self.fonts[self.find_font_index(c)];
+        (
+            font,
+            get_text_width(font, mark, self.font_size, self.features),
+        )
+    }
+
+    // The shaped advance of a glyph is given to the first character of it,
+    // so kerning is included and the other characters of a ligature have no width.
     pub fn compute_char_widths(&self) -> Vec<f64> {
-        self.rusttype_font
-            .glyphs_for(self.text.chars())
-            .map(|g| {
-                g.scaled(self.get_scale(self.rusttype_font))
-                    .h_metrics()
-                    .advance_width
+        let mut widths = Vec::new();
+        for (range, is_rtl) in self.split_text_to_level_runs() {
+            for (font_index, run) in self.split_line_to_runs(&self.text[range]) {
+                let mut run_widths = vec![0.0; run.chars().count()];
+                let char_indices: Vec<usize> = run.char_indices().map(|(index, _)| index).collect();
+                let glyphs =
+                    self.fonts[font_index].shape(&replace_tabs(run), self.features, is_rtl);
+                for glyph in glyphs {
+                    if let Ok(index) = char_indices.binary_search(&glyph.cluster) {
+                        run_widths[index] += to_mm_size(glyph.x_advance * self.font_size);
+                    }
+                }
+                widths.extend(run_widths);
+            }
+        }
+        widths
+    }
+
+    // Widths of tabs are replaced by the gaps to the next tab stops in each paragraph,
+    // or to multiples of 8 spaces past the last stop.
+    // Returns positions of bytes of tabs and the leader characters of their stops.
+    pub fn expand_tabs(
+        &self,
+        widths: &mut [f64],
+        tab_stops: &[TabStop],
+        space_width: f64,
+    ) -> Vec<(usize, char)> {
+        let chars: Vec<(usize, char)> = self.text.char_indices().collect();
+        let mut leaders = Vec::new();
+        let mut x = 0.0;
+        for (index, (position, c)) in chars.iter().enumerate() {
+            if *c == '\n' {
+                x = 0.0;
+                continue;
+            }
+            if *c == '\t' {
+                // The text after the tab until the next tab or newline.
+                let segment = chars[index + 1..]
+                    .iter()
+                    .zip(&widths[index + 1..])
+                    .take_while(|((_, c), _)| *c != '\t' && *c != '\n');
+                let mut segment_width = 0.0;
+                let mut decimal_width = None;
+                for ((_, c), width) in segment {
+                    if *c == '.' && decimal_width.is_none() {
+                        decimal_width = Some(segment_width);
+                    }
+                    segment_width += width;
+                }
+                widths[index] = match tab_stops.iter().find(|stop| stop.position > x) {
+                    Some(stop) => {
+                        let start = match stop.align {
+                            TabAlign::Left => stop.position,
+                            TabAlign::Right => stop.position - segment_width,
+                            TabAlign::Center => stop.position - segment_width / 2.0,
+                            TabAlign::Decimal => {
+                                stop.position - decimal_width.unwrap_or(segment_width)
+                            }
+                        };
+                        if let Some(leader) = stop.leader {
+                            leaders.push((*position, leader));
+                        }
+                        (start - x).max(0.0)
+                    }
+                    None => {
+                        let size = space_width * 8.0;
+                        ((x / size).floor() + 1.0) * size - x
+                    }
+                };
+            }
+            x += widths[index];
+        }
+        leaders
+    }
+
+    // Returns widths of characters in which tabs advance to the tab stops,
+    // and positions of tabs with their leaders.
+    pub fn compute_tabbed_char_widths(
+        &self,
+        tab_stops: &[TabStop],
+    ) -> (Vec<f64>, Vec<(usize, char)>) {
+        let mut widths = self.compute_char_widths();
+        let (_, space_width) = self.measure_mark(" ");
+        let leaders = self.expand_tabs(&mut widths, tab_stops, space_width);
+        (widths, leaders)
+    }
+
+    // Returns the width of the tab at the position resolved by `expand_tabs`.
+    fn get_tab_width(&self, widths: &[f64], position: usize) -> f64 {
+        self.get_line_width(widths, &(position..position + 1))
+    }
+
+    // Tabs advance by the widths resolved by `expand_tabs`.
+    fn expand_tab_glyphs(&self, glyphs: &mut [Glyph], start: usize, widths: &[f64]) {
+        let em = to_mm_size(self.font_size);
+        for glyph in glyphs {
+            let position = start + glyph.cluster;
+            if self.text[position..].starts_with('\t') {
+                glyph.x_advance = self.get_tab_width(widths, position) / em;
+            }
+        }
+    }
+
+    // Upright characters advance by 1 em in vertical lines,
+    // and the others advance by their widths because they are rotated.
+    pub fn compute_vertical_char_widths(&self) -> Vec<f64> {
+        let em = to_mm_size(self.font_size);
+        self.text
+            .chars()
+            .zip(self.compute_char_widths())
+            .map(|(c, width)| {
+                if is_upright(c) && width > 0.0 {
+                    em
+                } else {
+                    width
+                }
             })
-            .map(|w| to_mm_size((w * self.font_size) as f64))
             .collect()
     }
 
-    fn get_scale(&self, font: &Font) -> Scale {
-        let units_per_em = f32::from(font.units_per_em());
-        let v_metrics = font.v_metrics_unscaled();
-        let glyph_height = (v_metrics.ascent - v_metrics.descent) / units_per_em;
-        Scale::uniform(glyph_height)
+    // Splits the text into runs in logical order by the resolved direction,
+    // because right-to-left texts are shaped differently.
+    fn split_text_to_level_runs(&self) -> Vec<(Range<usize>, bool)> {
+        let levels = &self.bidi_info.levels;
+        let mut runs = Vec::new();
+        let mut from = 0;
+        for (index, _) in self.text.char_indices().skip(1) {
+            if levels[index].is_rtl() != levels[from].is_rtl() {
+                runs.push((from..index, levels[from].is_rtl()));
+                from = index;
+            }
+        }
+        if from < self.text.len() {
+            runs.push((from..self.text.len(), levels[from].is_rtl()));
+        }
+        runs
+    }
+
+    // Returns the first font that has the glyph,
+    // or the first font if no font has it.
+    fn find_font_index(&self, c: char) -> usize {
+        self.fonts
+            .iter()
+            .position(|font| font.has_glyph(c))
+            .unwrap_or(0)
+    }
+
+    // Splits the line into runs that can be written in the same font.
+    pub fn split_line_to_runs<'b>(&self, line: &'b str) -> Vec<(usize, &'b str)> {
+        split_text_by(line, |c| self.find_font_index(c))
     }
 }
 
@@ -92,35 +716,43 @@ impl<'a> Text<'a> {
         &self,
         pdf_page: &PdfPageReference,
         parent_rect: Option<&Rect>,
+        clip: &Clip,
     ) -> Result<(), Box<dyn std::error::Error>> {
         let rect = self
             .layout_tree
             .build_absolute_rect(self.node, parent_rect)?;
-        self.draw(pdf_page, rect)?;
+        self.draw(pdf_page, rect, clip)?;
         Ok(())
     }
 
-    fn load_font(&self) -> Result<&FontAssetItem, Error> {
-        let key = self.node.style.font_family;
-        self.assets
-            .fonts
-            .get(key)
-            .ok_or_else(|| Error::FontAssetNotFound(key.to_string()))
+    fn load_fonts(&self) -> Result<Vec<&FontAssetItem<'a>>, Error> {
+        let style = self.node.style;
+        let fonts =
+            self.assets
+                .fonts
+                .get_fallbacks(style.font_family, style.font_weight, style.font_style);
+        if fonts.is_empty() {
+            return Err(Error::FontAssetNotFound(style.font_family.to_string()));
+        }
+        Ok(fonts)
     }
 
-    fn draw(&self, pdf_page: &PdfPageReference, rect: Rect) -> Result<(), Error> {
-        if let Children::Text(text) = &self.node.element.children {
-            self.draw_border(pdf_page, &rect)?;
-            self.draw_text(text, pdf_page, &rect)?;
-            Ok(())
-        } else {
-            Err(Error::InvalidChildren(String::from("Text")))
-        }
+    fn draw(&self, pdf_page: &PdfPageReference, rect: Rect, clip: &Clip) -> Result<(), Error> {
+        let (text, rubies) =
+            flatten_children(&self.node.element.children, self.node.style.white_space)?;
+        self.draw_border(pdf_page, &rect, clip)?;
+        self.draw_text(&text, &rubies, pdf_page, &rect, clip)?;
+        Ok(())
     }
 
-    fn draw_border(&self, pdf_page: &PdfPageReference, rect: &Rect) -> Result<(), Error> {
+    fn draw_border(
+        &self,
+        pdf_page: &PdfPageReference,
+        rect: &Rect,
+        clip: &Clip,
+    ) -> Result<(), Error> {
         let style = self.node.style;
-        let rect = self.layout_tree.build_bottom_left_base_rect(&rect)?;
+        let rect = self.layout_tree.build_bottom_left_base_rect(rect)?;
 
         if let Some(border_color) = &style.border_color {
             Border {
@@ -128,38 +760,667 @@ impl<'a> Text<'a> {
                 border_color: &HexColor::new(border_color).into(),
                 rect: &rect,
                 pdf_page,
+                clip,
             }
             .draw();
         }
         Ok(())
     }
 
-    fn draw_text(&self, text: &str, pdf_page: &PdfPageReference, rect: &Rect) -> Result<(), Error> {
-        let font_data = self.load_font()?;
-        let font_size = self.node.style.font_size;
+    fn build_clip(&self, rect: &Rect, clip: &Clip) -> Result<Clip, Error> {
+        let style = self.node.style;
+        if style.overflow != Overflow::Hidden {
+            return Ok(clip.clone());
+        }
+        let rect = self.layout_tree.build_bottom_left_base_rect(rect)?;
+        Ok(clip.push(&rect, style.border_radius))
+    }
+
+    // Lines are split only by hard breaks in `Pre`.
+    fn get_max_line_width(&self, width: f64) -> f64 {
+        if self.node.style.white_space == WhiteSpace::Pre {
+            f64::MAX
+        } else {
+            width
+        }
+    }
+
+    // The `lang` attribute is used only in `Auto`, and unsupported languages are ignored.
+    fn find_hyphenation_points(&self, text: &str) -> Vec<usize> {
+        match self.node.style.hyphens {
+            Hyphens::None => vec![],
+            Hyphens::Manual => find_hyphenation_points(text, None),
+            Hyphens::Auto => {
+                let language: Option<Language> = get_attr(self.node.element, "lang");
+                find_hyphenation_points(text, language)
+            }
+        }
+    }
+
+    // Lines are broken to be even in `Justify`, or greedily if they can't be,
+    // e.g. when a word is wider than the line.
+    fn split_lines(&self, multi_line: &MultiLine, widths: &[f64]) -> Vec<Range<usize>> {
+        let style = self.node.style;
+        let is_optimal =
+            style.text_align == TextAlign::Justify && style.white_space != WhiteSpace::Pre;
+        let (_, hyphen_width) = multi_line.measure_mark(HYPHEN);
+        is_optimal
+            .then(|| multi_line.split_text_to_optimal_lines(widths, hyphen_width))
+            .flatten()
+            .unwrap_or_else(|| multi_line.split_text_to_multi_lines(widths, hyphen_width))
+    }
+
+    // Lines beyond `max_lines` are removed. In `Ellipsis`, lines that overflow the box are
+    // also removed, and the last line ends with an ellipsis if any line is removed.
+    // The lines which are wider than the box also end with it, e.g. in `Pre`.
+    fn truncate_lines(
+        &self,
+        multi_line: &MultiLine,
+        widths: &[f64],
+        lines: Vec<Range<usize>>,
+        (line_size, box_size): (f64, f64),
+        line_height: f64,
+    ) -> Vec<(Range<usize>, bool)> {
+        let style = self.node.style;
+        let is_ellipsis = style.text_overflow == TextOverflow::Ellipsis;
+        let (_, ellipsis_width) = multi_line.measure_mark(ELLIPSIS);
+        let mut max_lines = style.max_lines.unwrap_or(usize::MAX);
+        if is_ellipsis {
+            let pitch = to_mm_size(line_height);
+            let visible = ((box_size - to_mm_size(multi_line.font_size)) / pitch).floor();
+            max_lines = max_lines.min(visible.max(0.0) as usize + 1);
+        }
+        let count = lines.len().min(max_lines);
+        let is_truncated = lines.len() > count;
+        lines
+            .into_iter()
+            .take(count)
+            .enumerate()
+            .map(|(index, line)| {
+                let is_last = is_truncated && index + 1 == count;
+                let is_overflowed = multi_line.get_line_width(widths, &line) > line_size;
+                if is_ellipsis && (is_last || is_overflowed) {
+                    let max_width = line_size - ellipsis_width;
+                    (multi_line.truncate_line(widths, &line, max_width), true)
+                } else {
+                    (line, false)
+                }
+            })
+            .collect()
+    }
+
+    // Returns the offset of the line from the left by `text_align`.
+    fn compute_line_offset(&self, space: f64) -> f64 {
+        let style = self.node.style;
+        let is_rtl = style.direction == Direction::Rtl;
+        match style.text_align {
+            TextAlign::Center => space / 2.0,
+            TextAlign::Right => space,
+            TextAlign::Start | TextAlign::Justify if is_rtl => space,
+            TextAlign::End if !is_rtl => space,
+            _ => 0.0,
+        }
+    }
+
+    fn draw_text(
+        &self,
+        text: &str,
+        rubies: &[Ruby],
+        pdf_page: &PdfPageReference,
+        rect: &Rect,
+        clip: &Clip,
+    ) -> Result<(), Error> {
+        let fonts = self.load_fonts()?;
+        let clip = self.build_clip(rect, clip)?;
+        let features = parse_features(self.node.style.font_feature_settings)?;
+
+        let sizes = self.resolve_font_size(text, rubies, &fonts, &features, rect);
+        let multi_line = self.build_multi_line(text, rubies, fonts, &features, sizes.0, rect);
+        let pdf_layer = clip.add_layer(pdf_page);
+        match self.node.style.writing_mode {
+            WritingMode::HorizontalTb => {
+                self.draw_horizontal_lines(rubies, &multi_line, sizes.1, &pdf_layer, rect)
+            }
+            WritingMode::VerticalRl => {
+                self.draw_vertical_lines(&multi_line, sizes.1, &pdf_layer, rect)
+            }
+        }
+    }
+
+    // Lines are wrapped by the width of the box in horizontal lines,
+    // and by the height in vertical lines.
+    fn build_multi_line<'b>(
+        &self,
+        text: &'b str,
+        rubies: &[Ruby],
+        fonts: Vec<&'b FontAssetItem<'b>>,
+        features: &'b [Feature],
+        font_size: f64,
+        rect: &Rect,
+    ) -> MultiLine<'b> {
+        let style = self.node.style;
+        let (max_width, level) = match (style.writing_mode, style.direction) {
+            (WritingMode::VerticalRl, _) => (rect.height, Level::ltr()),
+            (WritingMode::HorizontalTb, Direction::Ltr) => (rect.width, Level::ltr()),
+            (WritingMode::HorizontalTb, Direction::Rtl) => (rect.width, Level::rtl()),
+        };
+        MultiLine {
+            text,
+            fonts,
+            features,
+            max_width: self.get_max_line_width(max_width),
+            font_size,
+            bidi_info: BidiInfo::new(text, Some(level)),
+            no_breaks: rubies.iter().map(|ruby| ruby.base.clone()).collect(),
+            hyphens: self.find_hyphenation_points(text),
+            break_opportunities: find_break_opportunities(text),
+        }
+    }
+
+    // Tabs advance to tab stops only in horizontal lines.
+    fn compute_widths(&self, multi_line: &MultiLine) -> Vec<f64> {
+        match self.node.style.writing_mode {
+            WritingMode::HorizontalTb => {
+                let (widths, _) = multi_line.compute_tabbed_char_widths(self.node.style.tab_stops);
+                widths
+            }
+            WritingMode::VerticalRl => multi_line.compute_vertical_char_widths(),
+        }
+    }
+
+    // Returns the font size and the line height. With `font_size_fit`, the largest size
+    // whose lines fit in the box is searched by bisection within `FONT_SIZE_PRECISION`.
+    fn resolve_font_size(
+        &self,
+        text: &str,
+        rubies: &[Ruby],
+        fonts: &[&FontAssetItem],
+        features: &[Feature],
+        rect: &Rect,
+    ) -> (f64, f64) {
+        let style = self.node.style;
+        let Some(fit) = style.font_size_fit else {
+            return (style.font_size, style.line_height);
+        };
+        let line_height = |font_size| scale_line_height(style, font_size);
+        let is_fitted = |font_size: f64| {
+            let multi_line =
+                self.build_multi_line(text, rubies, fonts.to_vec(), features, font_size, rect);
+            let widths = self.compute_widths(&multi_line);
+            self.is_fitted(&multi_line, &widths, rubies, line_height(font_size), rect)
+        };
+        let (mut min, mut max) = clamp_font_size_fit(fit);
+        if is_fitted(max) {
+            return (max, line_height(max));
+        }
+        while max - min > FONT_SIZE_PRECISION {
+            let size = (min + max) / 2.0;
+            if is_fitted(size) {
+                min = size;
+            } else {
+                max = size;
+            }
+        }
+        (min, line_height(min))
+    }
+
+    // Lines fit if they are within `max_lines` and the box including gaps for rubies.
+    fn is_fitted(
+        &self,
+        multi_line: &MultiLine,
+        widths: &[f64],
+        rubies: &[Ruby],
+        line_height: f64,
+        rect: &Rect,
+    ) -> bool {
+        let style = self.node.style;
+        let lines = self.split_lines(multi_line, widths);
+        let (line_size, box_size) = match style.writing_mode {
+            WritingMode::HorizontalTb => (rect.width, rect.height),
+            WritingMode::VerticalRl => (rect.height, rect.width),
+        };
+        let (_, hyphen_width) = multi_line.measure_mark(HYPHEN);
+        let is_within_lines = lines.iter().all(|line| {
+            let width = multi_line.get_line_width(widths, line);
+            if multi_line.is_hyphenated(line) {
+                width + hyphen_width <= line_size
+            } else {
+                width <= line_size
+            }
+        });
+        let gaps = match style.writing_mode {
+            WritingMode::HorizontalTb => lines
+                .iter()
+                .filter(|line| rubies.iter().any(|ruby| line.contains(&ruby.base.start)))
+                .count(),
+            WritingMode::VerticalRl => 0,
+        };
+        let size = to_mm_size(multi_line.font_size)
+            + lines.len().saturating_sub(1) as f64 * to_mm_size(line_height)
+            + gaps as f64 * to_mm_size(multi_line.font_size / 2.0);
+        is_within_lines && size <= box_size && lines.len() <= style.max_lines.unwrap_or(usize::MAX)
+    }
 
+    fn draw_horizontal_lines(
+        &self,
+        rubies: &[Ruby],
+        multi_line: &MultiLine,
+        line_height: f64,
+        pdf_layer: &PdfLayerReference,
+        rect: &Rect,
+    ) -> Result<(), Error> {
+        let (text, features, font_size) =
+            (multi_line.text, multi_line.features, multi_line.font_size);
+        let box_height = rect.height;
         let rect = rect.set_height(to_mm_size(font_size));
         let rect = self.layout_tree.build_bottom_left_base_rect(&rect)?;
 
-        let multi_line = MultiLine {
-            text,
-            rusttype_font: &font_data.rusttype,
-            max_width: rect.width,
-            font_size: font_size as f32,
+        let (widths, leaders) = multi_line.compute_tabbed_char_widths(self.node.style.tab_stops);
+        let lines = self.truncate_lines(
+            multi_line,
+            &widths,
+            self.split_lines(multi_line, &widths),
+            (rect.width, box_height),
+            line_height,
+        );
+        let em = to_mm_size(font_size);
+        let ruby_size = font_size / 2.0;
+        let write_mark = |mark: &str| -> Result<(), Error> {
+            let (font, _) = multi_line.measure_mark(mark);
+            let glyphs = font.shape(mark, features, false);
+            pdf_layer.set_font(font.embed(&glyphs, mark)?, font_size);
+            write_operations(pdf_layer, build_text_operations(&glyphs, font_size));
+            Ok(())
         };
-        let lines = multi_line.split_text_to_multi_lines();
+        let is_rtl_base = self.node.style.direction == Direction::Rtl;
 
-        let pdf_layer = pdf_page.add_layer("");
         pdf_layer.begin_text_section();
-        pdf_layer.set_font(&font_data.printpdf, font_size);
         pdf_layer.set_text_cursor(Mm(rect.x), Mm(rect.y));
-        pdf_layer.set_line_height(self.node.style.line_height);
+        pdf_layer.set_line_height(line_height);
 
-        for text in lines.iter() {
-            pdf_layer.write_text(*text, &font_data.printpdf);
+        // The cursor is moved relatively from the start of the previous line.
+        let mut line_offset = 0.0;
+        let mut baseline = rect.y;
+        let mut placed_rubies = Vec::new();
+        let mut placed_leaders = Vec::new();
+        for (line, has_ellipsis) in lines {
+            let mark = if has_ellipsis {
+                Some(ELLIPSIS)
+            } else if multi_line.is_hyphenated(&line) {
+                Some(HYPHEN)
+            } else {
+                None
+            };
+            let mark_width = mark.map_or(0.0, |mark| multi_line.measure_mark(mark).1);
+            let space = rect.width - multi_line.get_line_width(&widths, &line) - mark_width;
+            // Spaces are stretched to fill the line except the last line of the paragraph.
+            let spaces = count_spaces(&text[line.clone()]);
+            let is_justified = self.node.style.text_align == TextAlign::Justify
+                && spaces > 0
+                && !has_ellipsis
+                && !multi_line.is_paragraph_end(&line);
+            let word_spacing = if is_justified {
+                space / spaces as f64
+            } else {
+                0.0
+            };
+            let offset = self.compute_line_offset(space - word_spacing * spaces as f64);
+            let line_rubies: Vec<&Ruby> = rubies
+                .iter()
+                .filter(|ruby| line.contains(&ruby.base.start))
+                .collect();
+            // Lines that have rubies are moved down by the size of them.
+            let gap = if line_rubies.is_empty() {
+                0.0
+            } else {
+                to_mm_size(ruby_size)
+            };
+            if offset != line_offset || gap != 0.0 {
+                pdf_layer.set_text_cursor(Mm(offset - line_offset), Mm(-gap));
+                line_offset = offset;
+                baseline -= gap;
+            }
+            for ruby in line_rubies {
+                let before = line.start..ruby.base.start;
+                let x = rect.x
+                    + offset
+                    + multi_line.get_line_width(&widths, &before)
+                    + word_spacing * count_spaces(&text[before]) as f64;
+                let width = multi_line.get_line_width(&widths, &ruby.base)
+                    + word_spacing * count_spaces(&text[ruby.base.clone()]) as f64;
+                placed_rubies.push((ruby, x, width, baseline));
+            }
+            for (position, leader) in leaders.iter().filter(|(tab, _)| line.contains(tab)) {
+                let before = line.start..*position;
+                let x = rect.x
+                    + offset
+                    + multi_line.get_line_width(&widths, &before)
+                    + word_spacing * count_spaces(&text[before]) as f64;
+                let width = multi_line.get_tab_width(&widths, *position);
+                placed_leaders.push((*leader, x, width, baseline));
+            }
+            // The mark is written at the end of the line by the base direction.
+            if let Some(mark) = mark.filter(|_| is_rtl_base) {
+                write_mark(mark)?;
+            }
+            for (run, is_rtl) in reorder_line(&multi_line.bidi_info, line) {
+                // Runs of fonts are also reversed in a right-to-left run.
+                let mut start = run.start;
+                let mut runs: Vec<(usize, usize, &str)> = multi_line
+                    .split_line_to_runs(&text[run])
+                    .into_iter()
+                    .map(|(font_index, run)| {
+                        start += run.len();
+                        (font_index, start - run.len(), run)
+                    })
+                    .collect();
+                if is_rtl {
+                    runs.reverse();
+                }
+                for (font_index, start, run) in runs {
+                    let font = multi_line.fonts[font_index];
+                    let run_text = replace_tabs(run);
+                    let mut glyphs = font.shape(&run_text, features, is_rtl);
+                    stretch_spaces(&mut glyphs, run, word_spacing / em);
+                    multi_line.expand_tab_glyphs(&mut glyphs, start, &widths);
+                    pdf_layer.set_font(font.embed(&glyphs, &run_text)?, font_size);
+                    write_operations(pdf_layer, build_text_operations(&glyphs, font_size));
+                }
+            }
+            if let Some(mark) = mark.filter(|_| !is_rtl_base) {
+                write_mark(mark)?;
+            }
             pdf_layer.add_line_break();
+            baseline -= to_mm_size(line_height);
+        }
+        pdf_layer.end_text_section();
+
+        // Rubies are centered above their bases.
+        for (ruby, x, width, baseline) in placed_rubies {
+            let c = ruby.text.chars().next().unwrap_or(' ');
+            let font = multi_line.fonts[multi_line.find_font_index(c)];
+            let (ascender, descender) = font.get_vertical_metrics();
+            let ruby_width = get_text_width(font, &ruby.text, ruby_size, features);
+            let y = baseline + em * ascender - to_mm_size(ruby_size) * descender;
+            let position = (Mm(x + (width - ruby_width) / 2.0), Mm(y));
+            use_text(pdf_layer, font, &ruby.text, ruby_size, features, position)?;
+        }
+
+        // Leaders are repeated to fill gaps of tabs, and they are aligned to the end of them.
+        for (leader, x, width, baseline) in placed_leaders {
+            let font = multi_line.fonts[multi_line.find_font_index(leader)];
+            let leader = leader.to_string();
+            let leader_width = get_text_width(font, &leader, font_size, features);
+            let count = if leader_width > 0.0 {
+                (width / leader_width).floor() as usize
+            } else {
+                0
+            };
+            if count > 0 {
+                let position = (Mm(x + width - count as f64 * leader_width), Mm(baseline));
+                let leaders = leader.repeat(count);
+                use_text(pdf_layer, font, &leaders, font_size, features, position)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Lines run from top to bottom and progress from right to left by `line_height`.
+    // Texts aren't reordered by the bidi algorithm, and rubies aren't written
+    // and spaces aren't stretched by `Justify` in vertical lines.
+    fn draw_vertical_lines(
+        &self,
+        multi_line: &MultiLine,
+        line_height: f64,
+        pdf_layer: &PdfLayerReference,
+        rect: &Rect,
+    ) -> Result<(), Error> {
+        let (text, features, font_size) =
+            (multi_line.text, multi_line.features, multi_line.font_size);
+        let em = to_mm_size(font_size);
+        let rect = self.layout_tree.build_bottom_left_base_rect(rect)?;
+
+        let widths = multi_line.compute_vertical_char_widths();
+        // Marks such as the ellipsis are rotated same as Latin characters.
+        let (_, ellipsis_width) = multi_line.measure_mark(ELLIPSIS);
+        let lines = self.truncate_lines(
+            multi_line,
+            &widths,
+            self.split_lines(multi_line, &widths),
+            (rect.height, rect.width),
+            line_height,
+        );
+        let mut upright_features = features.to_vec();
+        upright_features.extend(parse_features(&["vert"])?);
+
+        pdf_layer.begin_text_section();
+        for (index, (line, has_ellipsis)) in lines.into_iter().enumerate() {
+            let center = rect.x + rect.width - em / 2.0 - index as f64 * to_mm_size(line_height);
+            let mut space = rect.height - multi_line.get_line_width(&widths, &line);
+            let mut line_text = replace_tabs(&text[line.clone()]);
+            if has_ellipsis {
+                space -= ellipsis_width;
+                line_text.push_str(ELLIPSIS);
+            } else if multi_line.is_hyphenated(&line) {
+                space -= multi_line.measure_mark(HYPHEN).1;
+                line_text.push_str(HYPHEN);
+            }
+            let mut y = rect.y + rect.height - self.compute_line_offset(space);
+
+            for (is_upright, run) in split_text_by(&line_text, is_upright) {
+                for (font_index, run) in multi_line.split_line_to_runs(run) {
+                    let font = multi_line.fonts[font_index];
+                    let (ascender, descender) = font.get_vertical_metrics();
+                    if is_upright {
+                        let glyphs = font.shape(run, &upright_features, false);
+                        pdf_layer.set_font(font.embed(&glyphs, run)?, font_size);
+                        // Each cluster is centered in a square of 1 em.
+                        let baseline = em * ascender / (ascender - descender);
+                        for cluster in split_clusters(&glyphs) {
+                            let width: f64 = cluster.iter().map(|glyph| glyph.x_advance).sum();
+                            set_text_matrix(
+                                pdf_layer,
+                                center - width * em / 2.0,
+                                y - baseline,
+                                false,
+                            );
+                            write_operations(pdf_layer, build_text_operations(cluster, font_size));
+                            y -= em;
+                        }
+                    } else {
+                        let glyphs = font.shape(run, features, false);
+                        pdf_layer.set_font(font.embed(&glyphs, run)?, font_size);
+                        // The baseline is moved so that rotated glyphs are centered in the line.
+                        let baseline = center - em * (ascender + descender) / 2.0;
+                        set_text_matrix(pdf_layer, baseline, y, true);
+                        write_operations(pdf_layer, build_text_operations(&glyphs, font_size));
+                        y -= glyphs.iter().map(|glyph| glyph.x_advance).sum::<f64>() * em;
+                    }
+                }
+            }
         }
         pdf_layer.end_text_section();
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Fonts aren't needed to break lines by widths of characters.
+    fn build_multi_line(text: &str, max_width: f64) -> MultiLine<'_> {
+        MultiLine {
+            text,
+            fonts: vec![],
+            features: &[],
+            max_width,
+            font_size: 10.0,
+            bidi_info: BidiInfo::new(text, None),
+            no_breaks: vec![],
+            hyphens: vec![],
+            break_opportunities: find_break_opportunities(text),
+        }
+    }
+
+    fn split_text(text: &str, max_width: f64) -> Vec<&str> {
+        let multi_line = build_multi_line(text, max_width);
+        let widths = vec![1.0; text.chars().count()];
+        multi_line
+            .split_text_to_multi_lines(&widths, 1.0)
+            .into_iter()
+            .map(|line| &text[line])
+            .collect()
+    }
+
+    #[test]
+    fn it_breaks_lines_between_latin_and_cjk_characters() {
+        assert_eq!(split_text("PDFを作る", 4.0), vec!["PDF", "を作る"]);
+        assert_eq!(split_text("PDFを作る", 5.0), vec!["PDFを", "作る"]);
+        assert_eq!(
+            split_text("日本語の PDF です", 5.0),
+            vec!["日本語の", "PDF", "です"]
+        );
+    }
+
+    #[test]
+    fn it_doesnt_break_lines_at_no_break_spaces() {
+        assert_eq!(split_text("ab\u{A0}c de", 6.0), vec!["ab\u{A0}c", "de"]);
+    }
+
+    #[test]
+    fn it_breaks_words_wider_than_the_line_between_characters() {
+        assert_eq!(
+            split_text("a abcdefghij", 4.0),
+            vec!["a", "abc", "def", "ghij"]
+        );
+    }
+
+    #[test]
+    fn it_leaves_words_wider_than_the_line_to_greedy_breaking() {
+        let text = "a abcdefghij";
+        let multi_line = build_multi_line(text, 4.0);
+        let widths = vec![1.0; text.chars().count()];
+        assert_eq!(multi_line.split_text_to_optimal_lines(&widths, 1.0), None);
+    }
+
+    #[test]
+    fn it_truncates_lines_to_fit_in_the_width() {
+        let text = "ab cd ef";
+        let multi_line = build_multi_line(text, f64::MAX);
+        let widths = vec![1.0; text.chars().count()];
+        let truncate = |max_width| &text[multi_line.truncate_line(&widths, &(0..5), max_width)];
+        assert_eq!(truncate(5.0), "ab cd");
+        assert_eq!(truncate(4.5), "ab c");
+        // Spaces before the ellipsis are also removed.
+        assert_eq!(truncate(3.5), "ab");
+        assert_eq!(truncate(0.5), "");
+    }
+
+    #[test]
+    fn it_breaks_cjk_texts_into_optimal_lines() {
+        let text = "日本語の文章です";
+        let multi_line = build_multi_line(text, 3.0);
+        let widths = vec![1.0; text.chars().count()];
+        let lines = multi_line
+            .split_text_to_optimal_lines(&widths, 1.0)
+            .unwrap();
+        let lines: Vec<&str> = lines.into_iter().map(|line| &text[line]).collect();
+        assert_eq!(lines, vec!["日本語", "の文章", "です"]);
+    }
+
+    #[test]
+    fn it_scales_line_height_by_font_size() {
+        let style = Style {
+            font_size: 10.0,
+            line_height: 12.0,
+            ..Style::DEFAULT
+        };
+        assert_eq!(scale_line_height(&style, 5.0), 6.0);
+        // `font_size` of zero doesn't make the line height NaN.
+        for font_size in [0.0, -10.0] {
+            let style = Style { font_size, ..style };
+            assert_eq!(scale_line_height(&style, 5.0), 12.0);
+        }
+    }
+
+    #[test]
+    fn it_clamps_font_size_fit_to_positive_sizes() {
+        let fit = |min, max| clamp_font_size_fit(FontSizeFit { min, max });
+        assert_eq!(fit(8.0, 30.0), (8.0, 30.0));
+        assert_eq!(fit(8.0, 4.0), (8.0, 8.0));
+        assert_eq!(fit(0.0, 30.0), (FONT_SIZE_PRECISION, 30.0));
+        assert_eq!(fit(-8.0, -4.0), (FONT_SIZE_PRECISION, FONT_SIZE_PRECISION));
+    }
+
+    // Characters including spaces are 1mm wide.
+    fn expand_tabs(text: &str, tab_stops: &[TabStop]) -> (Vec<f64>, Vec<(usize, char)>) {
+        let multi_line = build_multi_line(text, f64::MAX);
+        let mut widths = vec![1.0; text.chars().count()];
+        let leaders = multi_line.expand_tabs(&mut widths, tab_stops, 1.0);
+        (widths, leaders)
+    }
+
+    fn build_tab_stop(position: f64, align: TabAlign) -> TabStop {
+        TabStop {
+            position,
+            align,
+            leader: None,
+        }
+    }
+
+    #[test]
+    fn it_expands_tabs_to_left_tab_stops() {
+        let tab_stops = [build_tab_stop(10.0, TabAlign::Left)];
+        let (widths, _) = expand_tabs("ab\tcd", &tab_stops);
+        assert_eq!(widths, vec![1.0, 1.0, 8.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn it_expands_tabs_to_right_and_center_tab_stops() {
+        let tab_stops = [build_tab_stop(10.0, TabAlign::Right)];
+        let (widths, _) = expand_tabs("ab\tcd", &tab_stops);
+        assert_eq!(widths[2], 6.0);
+        let tab_stops = [build_tab_stop(10.0, TabAlign::Center)];
+        let (widths, _) = expand_tabs("ab\tcd", &tab_stops);
+        assert_eq!(widths[2], 7.0);
+    }
+
+    #[test]
+    fn it_expands_tabs_to_decimal_tab_stops() {
+        let tab_stops = [build_tab_stop(10.0, TabAlign::Decimal)];
+        // The decimal point is put at the tab stop.
+        let (widths, _) = expand_tabs("ab\t12.50", &tab_stops);
+        assert_eq!(widths[2], 6.0);
+        // The text without the decimal point is aligned to the right.
+        let (widths, _) = expand_tabs("ab\t1250", &tab_stops);
+        assert_eq!(widths[2], 4.0);
+    }
+
+    #[test]
+    fn it_returns_leaders_of_tab_stops() {
+        let tab_stops = [
+            TabStop {
+                leader: Some('.'),
+                ..build_tab_stop(5.0, TabAlign::Left)
+            },
+            build_tab_stop(10.0, TabAlign::Left),
+        ];
+        let (widths, leaders) = expand_tabs("a\tb\tc", &tab_stops);
+        assert_eq!(widths, vec![1.0, 4.0, 1.0, 4.0, 1.0]);
+        assert_eq!(leaders, vec![(1, '.')]);
+    }
+
+    #[test]
+    fn it_expands_tabs_past_the_last_tab_stop_by_eight_spaces() {
+        let tab_stops = [build_tab_stop(4.0, TabAlign::Left)];
+        let (widths, _) = expand_tabs("a\tb\tc\td", &tab_stops);
+        assert_eq!(widths, vec![1.0, 3.0, 1.0, 3.0, 1.0, 7.0, 1.0]);
+    }
+
+    #[test]
+    fn it_expands_tabs_from_the_start_of_each_paragraph() {
+        let (widths, _) = expand_tabs("abc\td\n\te", &[]);
+        assert_eq!(widths, vec![1.0, 1.0, 1.0, 5.0, 1.0, 1.0, 8.0, 1.0]);
+    }
+}